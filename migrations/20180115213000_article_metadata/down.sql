@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS article_tag;
+DROP TABLE IF EXISTS tag;
+ALTER TABLE article DROP COLUMN IF EXISTS draft;
+ALTER TABLE article DROP COLUMN IF EXISTS slug;
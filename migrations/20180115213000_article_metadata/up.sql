@@ -0,0 +1,13 @@
+ALTER TABLE article ADD COLUMN slug TEXT UNIQUE;
+ALTER TABLE article ADD COLUMN draft BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE tag (
+    id SERIAL PRIMARY KEY,
+    name TEXT UNIQUE NOT NULL
+);
+
+CREATE TABLE article_tag (
+    article INTEGER NOT NULL REFERENCES article(id) ON DELETE CASCADE,
+    tag INTEGER NOT NULL REFERENCES tag(id) ON DELETE CASCADE,
+    PRIMARY KEY (article, tag)
+);
@@ -0,0 +1 @@
+ALTER TABLE article ADD COLUMN summary TEXT;
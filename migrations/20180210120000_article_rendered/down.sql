@@ -0,0 +1,2 @@
+ALTER TABLE article DROP COLUMN IF EXISTS summary_html;
+ALTER TABLE article DROP COLUMN IF EXISTS body_html;
@@ -0,0 +1,2 @@
+ALTER TABLE article ADD COLUMN body_html TEXT;
+ALTER TABLE article ADD COLUMN summary_html TEXT;
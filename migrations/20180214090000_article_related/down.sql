@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS article_title_trgm;
+DROP INDEX IF EXISTS article_tag_tag;
+DROP INDEX IF EXISTS article_published_created_at;
@@ -0,0 +1,8 @@
+-- Creating pg_trgm takes a superuser before PostgreSQL 13. There, run this as one
+-- on the zakki database before applying the migrations:
+--   psql -U postgres -d zakki -c 'CREATE EXTENSION IF NOT EXISTS pg_trgm;'
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX article_published_created_at ON article (created_at, id) WHERE NOT draft;
+CREATE INDEX article_tag_tag ON article_tag (tag);
+CREATE INDEX article_title_trgm ON article USING gin (title gin_trgm_ops);
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS series_article;
+DROP TABLE IF EXISTS series;
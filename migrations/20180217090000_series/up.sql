@@ -0,0 +1,17 @@
+CREATE TABLE series (
+    id SERIAL PRIMARY KEY,
+    slug TEXT UNIQUE NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE TABLE series_article (
+    series INTEGER NOT NULL REFERENCES series(id) ON DELETE CASCADE,
+    article INTEGER UNIQUE NOT NULL REFERENCES article(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (series, article)
+);
+
+CREATE INDEX series_article_position ON series_article (series, position);
@@ -0,0 +1,6 @@
+DROP INDEX IF EXISTS article_featured;
+DROP INDEX IF EXISTS article_published_listing;
+
+ALTER TABLE article DROP COLUMN IF EXISTS promoted_at;
+ALTER TABLE article DROP COLUMN IF EXISTS featured;
+ALTER TABLE article DROP COLUMN IF EXISTS pinned;
@@ -0,0 +1,6 @@
+ALTER TABLE article ADD COLUMN pinned BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE article ADD COLUMN featured BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE article ADD COLUMN promoted_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX article_published_listing ON article (pinned DESC, created_at DESC, id DESC) WHERE NOT draft;
+CREATE INDEX article_featured ON article (created_at) WHERE featured AND NOT draft;
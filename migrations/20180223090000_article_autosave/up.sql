@@ -0,0 +1,11 @@
+CREATE TABLE article_autosave (
+    person INTEGER NOT NULL REFERENCES person(id) ON DELETE CASCADE,
+    -- NULL for an article not created yet.
+    article INTEGER REFERENCES article(id) ON DELETE CASCADE,
+    title TEXT NOT NULL,
+    body TEXT NOT NULL,
+    summary TEXT NOT NULL,
+    saved_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE UNIQUE INDEX article_autosave_person_article ON article_autosave (person, coalesce(article, 0));
@@ -0,0 +1,18 @@
+CREATE TABLE audit_log (
+    id SERIAL PRIMARY KEY,
+    action TEXT NOT NULL,
+    -- NULL when nobody was logged in, e.g. for a failed login.
+    actor INTEGER REFERENCES person(id) ON DELETE SET NULL,
+    -- Kept so that the entries still tell who it was after the person is deleted.
+    actor_name TEXT NOT NULL,
+    -- What the action was done to, e.g. the title of an article or the name given
+    -- at a failed login.
+    target TEXT NOT NULL,
+    target_id INTEGER,
+    ip TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX audit_log_created_at ON audit_log (created_at DESC, id DESC);
+CREATE INDEX audit_log_action ON audit_log (action, created_at DESC);
+CREATE INDEX audit_log_actor_name ON audit_log (actor_name, created_at DESC);
@@ -0,0 +1,468 @@
+use db::person::LOCKED_PASSWORD;
+use db::series::Series;
+use db::tag::Tag;
+use errors::*;
+
+use chrono::{DateTime, Local};
+use postgres::GenericConnection;
+
+use std::collections::HashMap;
+
+pub const VERSION: u32 = 1;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Archive {
+    pub version: u32,
+    pub persons: Vec<PersonEntry>,
+    pub articles: Vec<ArticleEntry>,
+    #[serde(default)]
+    pub series: Vec<SeriesEntry>,
+    #[serde(default)]
+    pub settings: Vec<SettingEntry>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct PersonEntry {
+    pub id: i32,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ArticleEntry {
+    pub id: i32,
+    pub title: String,
+    pub body: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slug: Option<String>,
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub featured: bool,
+    /// When the article was last pinned or featured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub promoted_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub author: i32,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SeriesEntry {
+    pub slug: String,
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    /// Ids of the articles in the series, in order.
+    #[serde(default)]
+    pub articles: Vec<i32>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SettingEntry {
+    pub name: String,
+    pub value: String,
+    pub updated_at: DateTime<Local>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IdMode {
+    /// Insert rows with the ids recorded in the archive.
+    Preserve,
+    /// Let the database assign new ids and rewrite references.
+    Remap,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportSummary {
+    pub persons_created: usize,
+    pub articles_created: usize,
+    pub articles_skipped: usize,
+    pub series_created: usize,
+    pub settings_created: usize,
+}
+
+pub fn export(conn: &GenericConnection, with_passwords: bool) -> Result<Archive> {
+    let persons = conn.query("SELECT id, name, password FROM person ORDER BY id", &[])?
+        .iter()
+        .map(|row| PersonEntry {
+            id: row.get(0),
+            name: row.get(1),
+            password: if with_passwords { Some(row.get(2)) } else { None },
+        })
+        .collect();
+    let mut articles = Vec::new();
+    for row in &conn.query(
+        "SELECT id, title, body, summary, slug, draft, author, created_at, updated_at,
+                                      pinned, featured, promoted_at
+                               FROM article ORDER BY id",
+        &[],
+    )? {
+        let id = row.get(0);
+        let tags = Tag::list_for_article(conn, id)?
+            .into_iter()
+            .map(|tag| tag.name)
+            .collect();
+        articles.push(ArticleEntry {
+            id: id,
+            title: row.get(1),
+            body: row.get(2),
+            summary: row.get(3),
+            slug: row.get(4),
+            draft: row.get(5),
+            pinned: row.get(9),
+            featured: row.get(10),
+            promoted_at: row.get(11),
+            tags: tags,
+            author: row.get(6),
+            created_at: row.get(7),
+            updated_at: row.get(8),
+        });
+    }
+    let mut series = Vec::new();
+    for row in &conn.query("SELECT id, slug, title, description FROM series ORDER BY id", &[])? {
+        let id: i32 = row.get(0);
+        let articles = conn.query(
+            "SELECT article FROM series_article WHERE series = $1 ORDER BY position",
+            &[&id],
+        )?
+            .iter()
+            .map(|row| row.get(0))
+            .collect();
+        series.push(SeriesEntry {
+            slug: row.get(1),
+            title: row.get(2),
+            description: row.get(3),
+            articles: articles,
+        });
+    }
+    let settings = conn.query("SELECT name, value, updated_at FROM setting ORDER BY name", &[])?
+        .iter()
+        .map(|row| SettingEntry {
+            name: row.get(0),
+            value: row.get(1),
+            updated_at: row.get(2),
+        })
+        .collect();
+    Ok(Archive {
+        version: VERSION,
+        persons: persons,
+        articles: articles,
+        series: series,
+        settings: settings,
+    })
+}
+
+/// Restores an archive. Persons are matched by name and articles by id (or by author,
+/// title and creation time when remapping), series by slug and settings by name, so
+/// importing the same archive twice is a no-op. Settings already stored are kept.
+pub fn import(conn: &GenericConnection, archive: &Archive, mode: IdMode) -> Result<ImportSummary> {
+    if archive.version > VERSION {
+        bail!("unsupported archive version: {}", archive.version);
+    }
+
+    let mut summary = ImportSummary::default();
+    let mut person_ids = HashMap::new();
+    for person in &archive.persons {
+        let rows = conn.query("SELECT id FROM person WHERE name = $1", &[&person.name])?;
+        let id: i32 = match rows.iter().next() {
+            Some(row) => row.get(0),
+            None => {
+                let password = person
+                    .password
+                    .as_ref()
+                    .map(|p| p.as_str())
+                    .unwrap_or(LOCKED_PASSWORD);
+                let rows = match mode {
+                    IdMode::Preserve => conn.query(
+                        "INSERT INTO person (id, name, password) VALUES ($1, $2, $3) RETURNING id",
+                        &[&person.id, &person.name, &password],
+                    ),
+                    IdMode::Remap => conn.query(
+                        "INSERT INTO person (name, password) VALUES ($1, $2) RETURNING id",
+                        &[&person.name, &password],
+                    ),
+                }.chain_err(|| format!("failed to import person {}", person.name))?;
+                summary.persons_created += 1;
+                rows.iter()
+                    .next()
+                    .map(|row| row.get(0))
+                    .chain_err(|| "failed to get person creation result")?
+            }
+        };
+        person_ids.insert(person.id, id);
+    }
+
+    let mut article_ids = HashMap::new();
+    for article in &archive.articles {
+        let author = person_ids
+            .get(&article.author)
+            .chain_err(|| format!("unknown author of article {}", article.id))?;
+        let rows = match mode {
+            IdMode::Preserve => conn.query(
+                "INSERT INTO article
+                                   (id, title, body, summary, slug, draft, author, created_at,
+                                    updated_at, pinned, featured, promoted_at)
+                               VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                               ON CONFLICT DO NOTHING
+                               RETURNING id",
+                &[
+                    &article.id,
+                    &article.title,
+                    &article.body,
+                    &article.summary,
+                    &article.slug,
+                    &article.draft,
+                    author,
+                    &article.created_at,
+                    &article.updated_at,
+                    &article.pinned,
+                    &article.featured,
+                    &article.promoted_at,
+                ],
+            )?,
+            IdMode::Remap => conn.query(
+                "INSERT INTO article
+                                   (title, body, summary, slug, draft, author, created_at,
+                                    updated_at, pinned, featured, promoted_at)
+                               SELECT $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11
+                               WHERE NOT EXISTS (
+                                   SELECT 1 FROM article
+                                   WHERE author = $6 AND title = $1 AND created_at = $7)
+                               ON CONFLICT DO NOTHING
+                               RETURNING id",
+                &[
+                    &article.title,
+                    &article.body,
+                    &article.summary,
+                    &article.slug,
+                    &article.draft,
+                    author,
+                    &article.created_at,
+                    &article.updated_at,
+                    &article.pinned,
+                    &article.featured,
+                    &article.promoted_at,
+                ],
+            )?,
+        };
+        let id: i32 = match rows.iter().next() {
+            Some(row) => {
+                let id = row.get(0);
+                Tag::set_for_article(conn, id, &article.tags)?;
+                summary.articles_created += 1;
+                id
+            }
+            None => {
+                summary.articles_skipped += 1;
+                match mode {
+                    IdMode::Preserve => article.id,
+                    IdMode::Remap => conn.query(
+                        "SELECT id FROM article
+                                       WHERE author = $1 AND title = $2 AND created_at = $3",
+                        &[author, &article.title, &article.created_at],
+                    )?
+                        .iter()
+                        .next()
+                        .map(|row| row.get(0))
+                        .chain_err(|| format!("failed to find imported article {}", article.id))?,
+                }
+            }
+        };
+        article_ids.insert(article.id, id);
+    }
+
+    for series in &archive.series {
+        if Series::find_by_slug(conn, &series.slug)?.is_some() {
+            continue;
+        }
+        let ids = series
+            .articles
+            .iter()
+            .map(|id| {
+                article_ids
+                    .get(id)
+                    .cloned()
+                    .chain_err(|| format!("unknown article {} in series {}", id, series.slug))
+            })
+            .collect::<Result<Vec<i32>>>()?;
+        let created = Series::create(conn, &series.slug, &series.title, &series.description)?;
+        Series::set_articles(conn, created.id, &ids)?;
+        summary.series_created += 1;
+    }
+
+    for setting in &archive.settings {
+        summary.settings_created += conn.execute(
+            "INSERT INTO setting (name, value, updated_at) VALUES ($1, $2, $3)
+                               ON CONFLICT (name) DO NOTHING",
+            &[&setting.name, &setting.value, &setting.updated_at],
+        )? as usize;
+    }
+
+    if mode == IdMode::Preserve {
+        conn.execute("SELECT setval('person_id_seq', MAX(id)) FROM person", &[])?;
+        conn.execute("SELECT setval('article_id_seq', MAX(id)) FROM article", &[])?;
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use db::testutil;
+
+    #[test]
+    fn export_omits_passwords() {
+        testutil::with_db(|conn| {
+            let archive = export(conn, false).unwrap();
+            assert_eq!(archive.version, VERSION);
+            assert!(archive.persons.iter().all(|p| p.password.is_none()));
+        });
+    }
+
+    #[test]
+    fn import_is_idempotent() {
+        testutil::with_db(|conn| {
+            conn.execute(
+                "INSERT INTO article (title, body, author, created_at, updated_at)
+                          VALUES ($1, $2, $3, '2000-01-02 03:04:05.006+09', '2017-12-01 12:34:56.789+09')",
+                &[&"title", &"body", &1],
+            ).unwrap();
+            let archive = export(conn, true).unwrap();
+            for &mode in &[IdMode::Preserve, IdMode::Remap] {
+                let summary = import(conn, &archive, mode).unwrap();
+                assert_eq!(summary.persons_created, 0);
+                assert_eq!(summary.articles_created, 0);
+                assert_eq!(summary.articles_skipped, archive.articles.len());
+                assert_eq!(summary.series_created, 0);
+                assert_eq!(summary.settings_created, 0);
+            }
+        });
+    }
+
+    #[test]
+    fn round_trip() {
+        testutil::with_db(|conn| {
+            conn.execute(
+                "INSERT INTO article (title, body, author, created_at, updated_at, pinned, featured,
+                                      promoted_at)
+                          VALUES ('pinned', 'body', 1, '2000-01-02 03:04:05.006+09',
+                                  '2017-12-01 12:34:56.789+09', true, true,
+                                  '2018-02-20 09:00:00+09')",
+                &[],
+            ).unwrap();
+            let ids: Vec<i32> = conn.query(
+                "INSERT INTO article (title, body, author, created_at, updated_at)
+                          VALUES ('first', 'body', 1, '2000-01-01 00:00:00+09',
+                                  '2000-01-01 00:00:00+09'),
+                                 ('second', 'body', 1, '2000-01-02 00:00:00+09',
+                                  '2000-01-02 00:00:00+09')
+                          RETURNING id",
+                &[],
+            ).unwrap()
+                .iter()
+                .map(|row| row.get(0))
+                .collect();
+            let series = Series::create(conn, "series", "Series", "description").unwrap();
+            conn.execute(
+                "INSERT INTO setting (name, value, updated_at)
+                          VALUES ('site_name', 'blog', '2018-03-01 00:00:00+09')
+                          ON CONFLICT (name) DO UPDATE SET value = EXCLUDED.value",
+                &[],
+            ).unwrap();
+            Series::set_articles(conn, series.id, &[ids[1], ids[0]]).unwrap();
+            let archive = export(conn, true).unwrap();
+            let pinned = archive.articles.iter().find(|a| a.title == "pinned").unwrap();
+            assert!(pinned.pinned && pinned.featured && pinned.promoted_at.is_some());
+
+            let entry = archive.series.iter().find(|s| s.slug == "series").unwrap();
+            assert_eq!(entry.articles, vec![ids[1], ids[0]]);
+
+            assert!(archive.settings.iter().any(|s| s.name == "site_name" && s.value == "blog"));
+
+            conn.execute("DELETE FROM setting", &[]).unwrap();
+            conn.execute("DELETE FROM series", &[]).unwrap();
+            conn.execute("DELETE FROM article", &[]).unwrap();
+            import(conn, &archive, IdMode::Preserve).unwrap();
+            assert_eq!(export(conn, true).unwrap(), archive);
+        });
+    }
+
+    #[test]
+    fn import_remaps_ids() {
+        testutil::with_db(|conn| {
+            let archive = Archive {
+                version: VERSION,
+                persons: vec![
+                    PersonEntry {
+                        id: 100,
+                        name: "imported".to_owned(),
+                        password: None,
+                    },
+                ],
+                articles: vec![
+                    ArticleEntry {
+                        id: 200,
+                        title: "title".to_owned(),
+                        body: "body".to_owned(),
+                        summary: None,
+                        slug: None,
+                        draft: false,
+                        pinned: false,
+                        featured: false,
+                        promoted_at: None,
+                        tags: vec![],
+                        author: 100,
+                        created_at: "2000-1-2T03:04:05.006+09:00"
+                            .parse::<DateTime<Local>>()
+                            .unwrap(),
+                        updated_at: "2017-12-1T12:34:56.789+09:00"
+                            .parse::<DateTime<Local>>()
+                            .unwrap(),
+                    },
+                ],
+                series: vec![
+                    SeriesEntry {
+                        slug: "imported".to_owned(),
+                        title: "Imported".to_owned(),
+                        description: String::new(),
+                        articles: vec![200],
+                    },
+                ],
+                settings: vec![],
+            };
+            let summary = import(conn, &archive, IdMode::Remap).unwrap();
+            assert_eq!(
+                summary,
+                ImportSummary {
+                    persons_created: 1,
+                    articles_created: 1,
+                    articles_skipped: 0,
+                    series_created: 1,
+                    settings_created: 0,
+                }
+            );
+            let created_at: DateTime<Local> = conn.query(
+                "SELECT a.created_at FROM article a JOIN person p ON p.id = a.author
+                              WHERE p.name = 'imported'",
+                &[],
+            ).unwrap()
+                .get(0)
+                .get(0);
+            assert_eq!(created_at, archive.articles[0].created_at);
+
+            let series = Series::find_by_slug(conn, "imported").unwrap().unwrap();
+            let parts = Series::parts(conn, series.id).unwrap();
+            assert_eq!(parts.len(), 1);
+            assert_eq!(parts[0].title, "title");
+        });
+    }
+}
@@ -0,0 +1,169 @@
+use archive::{self, IdMode};
+use db;
+use db::article::Article;
+use db::person::Person;
+use errors::*;
+use generate;
+use import::{self, Report};
+use views::{self, Site};
+use views::card::Cards;
+
+use rocket;
+use rocket::config::Config;
+use serde_json;
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+const USAGE: &'static str = "usage:
+    zakki                                     start the server
+    zakki export <file> [--with-passwords]    write all persons and articles to <file>
+    zakki import <file> [--remap-ids]         restore an archive written by export
+    zakki import-markdown <dir> <author>      import Markdown posts with front matter from <dir>
+    zakki import-wordpress <file> [<redirects>]
+                                              import posts from a WordPress export and write
+                                              old URL to new path redirects to <redirects>
+    zakki generate <dir>                      render the public site as static files into <dir>
+    zakki render                              render the Markdown of all articles again, e.g.
+                                              after changing markdown_extensions";
+
+pub fn run(args: &[String]) -> Result<()> {
+    let (command, args) = args.split_first().chain_err(|| USAGE)?;
+    match command.as_str() {
+        "export" => export(args),
+        "import" => import(args),
+        "import-markdown" => import_markdown(args),
+        "import-wordpress" => import_wordpress(args),
+        "generate" => generate(args),
+        "render" => render(args),
+        _ => bail!(USAGE),
+    }
+}
+
+fn load_config() -> Config {
+    rocket::ignite().config().clone()
+}
+
+fn connect(config: &Config) -> Result<db::Connection> {
+    let database_url = config
+        .get_str("database_url")
+        .map(|url| url.to_owned())
+        .chain_err(|| "database_url is not configured")?;
+    let pool = db::init_pool(&database_url)?;
+    Ok(db::Connection::from(pool.get()?))
+}
+
+fn export(args: &[String]) -> Result<()> {
+    let path = args.first().chain_err(|| USAGE)?;
+    let with_passwords = args[1..].iter().any(|arg| arg == "--with-passwords");
+    let conn = connect(&load_config())?;
+    let archive = archive::export(&*conn, with_passwords)?;
+    serde_json::to_writer_pretty(File::create(path)?, &archive)?;
+    println!(
+        "exported {} persons, {} articles, {} series and {} settings to {}",
+        archive.persons.len(),
+        archive.articles.len(),
+        archive.series.len(),
+        archive.settings.len(),
+        path
+    );
+    Ok(())
+}
+
+fn import(args: &[String]) -> Result<()> {
+    let path = args.first().chain_err(|| USAGE)?;
+    let mode = if args[1..].iter().any(|arg| arg == "--remap-ids") {
+        IdMode::Remap
+    } else {
+        IdMode::Preserve
+    };
+    let archive: archive::Archive = serde_json::from_reader(File::open(path)?)?;
+    let conn = connect(&load_config())?;
+    let tx = conn.transaction()?;
+    let summary = archive::import(&tx, &archive, mode)?;
+    tx.commit()?;
+    println!(
+        "created {} persons, {} articles, {} series and {} settings, skipped {} existing articles",
+        summary.persons_created,
+        summary.articles_created,
+        summary.series_created,
+        summary.settings_created,
+        summary.articles_skipped
+    );
+    Ok(())
+}
+
+fn import_markdown(args: &[String]) -> Result<()> {
+    if args.len() != 2 {
+        bail!(USAGE);
+    }
+    let conn = connect(&load_config())?;
+    let tx = conn.transaction()?;
+    let author = Person::get_by_name(&tx, &args[1])?;
+    let report = import::markdown::import_dir(&tx, Path::new(&args[0]), &author)?;
+    tx.commit()?;
+    print_report(&report);
+    Ok(())
+}
+
+fn import_wordpress(args: &[String]) -> Result<()> {
+    let path = args.first().chain_err(|| USAGE)?;
+    let conn = connect(&load_config())?;
+    let tx = conn.transaction()?;
+    let (report, redirects) = import::wordpress::import(&tx, File::open(path)?)?;
+    tx.commit()?;
+    let mut out: Box<Write> = match args.get(1) {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout()),
+    };
+    for &(ref old, ref new) in &redirects {
+        writeln!(out, "{}\t{}", old, new)?;
+    }
+    print_report(&report);
+    Ok(())
+}
+
+fn generate(args: &[String]) -> Result<()> {
+    let dir = args.first().chain_err(|| USAGE)?;
+    let config = load_config();
+    let conn = connect(&config)?;
+    let site = Site::load(&config, &*conn)?;
+    let cards = Cards::from_site(&site)?;
+    let pages = generate::generate(&*conn, &site, cards.as_ref(), Path::new(dir))?;
+    println!("wrote {} pages to {}", pages, dir);
+    Ok(())
+}
+
+fn render(args: &[String]) -> Result<()> {
+    if !args.is_empty() {
+        bail!(USAGE);
+    }
+    let config = load_config();
+    let conn = connect(&config)?;
+    let site = Site::from_config(&config)?;
+    let tx = conn.transaction()?;
+    Article::clear_rendered(&tx)?;
+    let mut articles = Article::list(&tx)?;
+    for article in &mut articles {
+        views::prepare(&tx, article, site.extensions)?;
+    }
+    tx.commit()?;
+    println!("rendered {} articles", articles.len());
+    Ok(())
+}
+
+fn print_report(report: &Report) {
+    for &(ref source, ref reason) in &report.skipped {
+        println!("skipped {}: {}", source, reason);
+    }
+    for &(ref source, ref reason) in &report.invalid {
+        println!("invalid {}: {}", source, reason);
+    }
+    println!(
+        "created {}, skipped {}, invalid {}",
+        report.created.len(),
+        report.skipped.len(),
+        report.invalid.len()
+    );
+}
@@ -0,0 +1,289 @@
+//! Compresses text responses with gzip or brotli, whichever the client prefers.
+
+use errors::*;
+use views;
+
+use brotli::CompressorWriter;
+use flate2::Compression as GzipLevel;
+use flate2::write::GzEncoder;
+use rocket::config::{Config, ConfigError};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{ContentType, Status};
+use rocket::{Request, Response};
+use sha2::{Digest, Sha256};
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::io::Cursor;
+use std::path::Path;
+
+/// Responses smaller than this many bytes are sent as they are by default.
+const MIN_SIZE: usize = 1024;
+const BROTLI_QUALITY: u32 = 9;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    fn name(&self) -> &'static str {
+        match *self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+
+    fn encode(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match *self {
+            Encoding::Brotli => {
+                let mut out = Vec::new();
+                {
+                    let mut writer = CompressorWriter::new(&mut out, 4096, BROTLI_QUALITY, 22);
+                    writer.write_all(data)?;
+                }
+                Ok(out)
+            }
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), GzipLevel::default());
+                encoder.write_all(data)?;
+                Ok(encoder.finish()?)
+            }
+        }
+    }
+}
+
+/// Picks the encoding with the highest q-value in an `Accept-Encoding` header,
+/// preferring brotli on a tie.
+pub fn negotiate(accept_encoding: &str) -> Option<Encoding> {
+    let mut brotli = None;
+    let mut gzip = None;
+    let mut any = None;
+    for item in accept_encoding.split(',') {
+        let mut parts = item.split(';').map(|part| part.trim());
+        let name = parts.next().unwrap_or("").to_lowercase();
+        let q = parts
+            .filter_map(|param| {
+                if param.starts_with("q=") {
+                    param[2..].parse::<f32>().ok()
+                } else {
+                    None
+                }
+            })
+            .next()
+            .unwrap_or(1.0);
+        match name.as_str() {
+            "br" => brotli = Some(q),
+            "gzip" | "x-gzip" => gzip = Some(q),
+            "*" => any = Some(q),
+            _ => {}
+        }
+    }
+    let brotli = brotli.or(any).unwrap_or(0.0);
+    let gzip = gzip.or(any).unwrap_or(0.0);
+    if brotli > 0.0 && brotli >= gzip {
+        Some(Encoding::Brotli)
+    } else if gzip > 0.0 {
+        Some(Encoding::Gzip)
+    } else {
+        None
+    }
+}
+
+/// Whether responses of a type are worth compressing. Images other than SVG,
+/// archives and fonts are compressed already.
+fn is_compressible(content_type: &ContentType) -> bool {
+    match (content_type.top().as_str(), content_type.sub().as_str()) {
+        ("text", _) => true,
+        ("image", "svg+xml") => true,
+        ("application", sub) => {
+            ["javascript", "json", "xml", "rss+xml", "atom+xml", "manifest+json"].contains(&sub)
+        }
+        _ => false,
+    }
+}
+
+#[derive(Debug, Default)]
+struct Encoded {
+    brotli: Vec<u8>,
+    gzip: Vec<u8>,
+}
+
+/// A fairing compressing text responses. Files under `static/` can be compressed
+/// once at startup; they are recognized by their content, so responses of any route
+/// serving them use the precompressed data.
+#[derive(Debug)]
+pub struct Compression {
+    min_size: usize,
+    precompressed: HashMap<Vec<u8>, Encoded>,
+}
+
+impl Compression {
+    /// Reads `compression_min_size` and `precompress_static` (default true), which
+    /// covers the static files of the theme as well.
+    pub fn from_config(config: &Config) -> Result<Compression> {
+        let min_size = match config.get_int("compression_min_size") {
+            Ok(size) if size >= 0 => size as usize,
+            Err(ConfigError::NotFound) => MIN_SIZE,
+            _ => bail!("compression_min_size must be a non-negative integer"),
+        };
+        let precompress = match config.get_bool("precompress_static") {
+            Ok(precompress) => precompress,
+            Err(ConfigError::NotFound) => true,
+            Err(_) => bail!("precompress_static must be a boolean"),
+        };
+        let mut compression = Compression {
+            min_size: min_size,
+            precompressed: HashMap::new(),
+        };
+        if precompress {
+            for dir in views::theme::asset_dirs(config)? {
+                if dir.is_dir() {
+                    compression.precompress_dir(&dir)?;
+                }
+            }
+        }
+        Ok(compression)
+    }
+
+    fn precompress_dir(&mut self, dir: &Path) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                self.precompress_dir(&path)?;
+                continue;
+            }
+            let compressible = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .and_then(ContentType::from_extension)
+                .map(|content_type| is_compressible(&content_type))
+                .unwrap_or(false);
+            if !compressible {
+                continue;
+            }
+            let mut data = Vec::new();
+            File::open(&path)?.read_to_end(&mut data)?;
+            if data.len() < self.min_size {
+                continue;
+            }
+            let encoded = Encoded {
+                brotli: Encoding::Brotli.encode(&data)?,
+                gzip: Encoding::Gzip.encode(&data)?,
+            };
+            self.precompressed.insert(digest(&data), encoded);
+        }
+        Ok(())
+    }
+
+    fn compress(&self, data: &[u8], encoding: Encoding) -> Result<Vec<u8>> {
+        if let Some(encoded) = self.precompressed.get(&digest(data)) {
+            return Ok(match encoding {
+                Encoding::Brotli => encoded.brotli.clone(),
+                Encoding::Gzip => encoded.gzip.clone(),
+            });
+        }
+        encoding.encode(data)
+    }
+}
+
+fn digest(data: &[u8]) -> Vec<u8> {
+    Sha256::digest(data).as_slice().to_owned()
+}
+
+impl Fairing for Compression {
+    fn info(&self) -> Info {
+        Info {
+            name: "Response compression",
+            kind: Kind::Response,
+        }
+    }
+
+    fn on_response(&self, request: &Request, response: &mut Response) {
+        if response.status() != Status::Ok
+            || response.headers().get_one("Content-Encoding").is_some()
+        {
+            return;
+        }
+        match response.content_type() {
+            Some(ref content_type) if is_compressible(content_type) => {}
+            _ => return,
+        }
+        // The body depends on the request header from here on, even when it ends
+        // up uncompressed.
+        let vary = match response.headers().get_one("Vary") {
+            Some(vary) => format!("{}, Accept-Encoding", vary),
+            None => "Accept-Encoding".to_owned(),
+        };
+        response.set_raw_header("Vary", vary);
+        let encoding = match request.headers().get_one("Accept-Encoding").and_then(negotiate) {
+            Some(encoding) => encoding,
+            None => return,
+        };
+        let body = match response.body_bytes() {
+            Some(body) => body,
+            None => return,
+        };
+        let compressed = if body.len() < self.min_size {
+            None
+        } else {
+            self.compress(&body, encoding).ok()
+        };
+        match compressed {
+            Some(compressed) => {
+                response.set_raw_header("Content-Encoding", encoding.name());
+                response.set_sized_body(Cursor::new(compressed));
+            }
+            None => response.set_sized_body(Cursor::new(body)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiate_encoding() {
+        assert_eq!(negotiate("gzip, deflate, br"), Some(Encoding::Brotli));
+        assert_eq!(negotiate("gzip, deflate"), Some(Encoding::Gzip));
+        assert_eq!(negotiate("br;q=0, gzip;q=0.5"), Some(Encoding::Gzip));
+        assert_eq!(negotiate("br;q=0.1, gzip;q=1"), Some(Encoding::Gzip));
+        assert_eq!(negotiate("br;q=0.5, gzip;q=0.5"), Some(Encoding::Brotli));
+        assert_eq!(negotiate("*"), Some(Encoding::Brotli));
+        assert_eq!(negotiate("*;q=0, gzip"), Some(Encoding::Gzip));
+        assert_eq!(negotiate("identity"), None);
+        assert_eq!(negotiate(""), None);
+    }
+
+    #[test]
+    fn compressible() {
+        assert!(is_compressible(&ContentType::HTML));
+        assert!(is_compressible(&ContentType::CSS));
+        assert!(is_compressible(&ContentType::JavaScript));
+        assert!(is_compressible(&ContentType::new("application", "rss+xml")));
+        assert!(!is_compressible(&ContentType::PNG));
+        assert!(!is_compressible(&ContentType::new("font", "woff2")));
+    }
+
+    #[test]
+    fn precompressed() {
+        let data = "body { color: black; }\n".repeat(100);
+        let mut compression = Compression {
+            min_size: MIN_SIZE,
+            precompressed: HashMap::new(),
+        };
+        compression.precompressed.insert(
+            digest(data.as_bytes()),
+            Encoded {
+                brotli: b"br".to_vec(),
+                gzip: b"gz".to_vec(),
+            },
+        );
+        assert_eq!(compression.compress(data.as_bytes(), Encoding::Gzip).unwrap(), b"gz");
+        let other = compression.compress(b"other", Encoding::Gzip).unwrap();
+        assert_eq!(other, Encoding::Gzip.encode(b"other").unwrap());
+    }
+}
@@ -1,17 +1,111 @@
 use errors::*;
 use super::person::Person;
+use super::series::{Series, SeriesParts};
+use super::tag::Tag;
 
 use chrono::{DateTime, Local};
 use postgres::GenericConnection;
+use postgres::rows::Row;
 
 #[derive(Debug, PartialEq)]
 pub struct Article {
     pub id: i32,
     pub title: String,
     pub body: String,
+    pub summary: Option<String>,
+    pub slug: Option<String>,
+    pub draft: bool,
+    /// Listed before the other articles on the index.
+    pub pinned: bool,
+    /// Listed in a section of its own on the front page.
+    pub featured: bool,
     pub created_at: DateTime<Local>,
     pub updated_at: DateTime<Local>,
     pub author: Person,
+    /// HTML rendered from the Markdown, unless the article has changed since.
+    pub rendered: Option<Rendered>,
+}
+
+/// HTML of an article body and of the summary shown on the index, cached so that
+/// Markdown is not rendered on every request.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rendered {
+    pub body: String,
+    /// `None` when the index shows the whole body.
+    pub summary: Option<String>,
+}
+
+/// The title and date of an article, enough to link to it.
+#[derive(Debug, PartialEq)]
+pub struct ArticleLink {
+    pub id: i32,
+    pub title: String,
+    pub created_at: DateTime<Local>,
+}
+
+/// Links shown under an article to other published articles.
+#[derive(Debug, Default, PartialEq)]
+pub struct Navigation {
+    /// The article written just before.
+    pub previous: Option<ArticleLink>,
+    /// The article written just after.
+    pub next: Option<ArticleLink>,
+    pub related: Vec<ArticleLink>,
+    /// The series the article is part of.
+    pub series: Option<SeriesParts>,
+}
+
+/// An article brought in from another blog, keeping its original dates.
+#[derive(Debug, PartialEq)]
+pub struct ImportedArticle {
+    pub title: String,
+    pub body: String,
+    pub summary: Option<String>,
+    pub slug: String,
+    pub draft: bool,
+    pub tags: Vec<String>,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>,
+}
+
+/// The order of the index, which the published articles are listed in.
+const LISTING_ORDER: &'static str = "a.pinned DESC, a.created_at DESC, a.id DESC";
+
+const SELECT_ARTICLE: &'static str =
+    "SELECT a.id, a.title, a.body, a.summary, a.slug, a.draft, a.created_at, a.updated_at,
+                               p.id, p.name, a.body_html, a.summary_html, a.pinned, a.featured
+                               FROM article a JOIN person p ON p.id = a.author";
+
+fn from_row(row: &Row) -> Article {
+    Article {
+        id: row.get(0),
+        title: row.get(1),
+        body: row.get(2),
+        summary: row.get(3),
+        slug: row.get(4),
+        draft: row.get(5),
+        pinned: row.get(12),
+        featured: row.get(13),
+        created_at: row.get(6),
+        updated_at: row.get(7),
+        author: Person {
+            id: row.get(8),
+            name: row.get(9),
+        },
+        rendered: row.get::<_, Option<String>>(10).map(|body| Rendered {
+            body: body,
+            summary: row.get(11),
+        }),
+    }
+}
+
+/// Reads an `ArticleLink` from the id, title and creation time of a row.
+pub fn link_from_row(row: &Row) -> ArticleLink {
+    ArticleLink {
+        id: row.get(0),
+        title: row.get(1),
+        created_at: row.get(2),
+    }
 }
 
 impl Article {
@@ -19,13 +113,14 @@ impl Article {
         conn: &GenericConnection,
         title: &str,
         body: &str,
+        summary: Option<&str>,
         author: &Person,
     ) -> Result<Article> {
         let rows = conn.query(
-            "INSERT INTO article (title, body, author, created_at, updated_at)
-                               VALUES ($1, $2, $3, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+            "INSERT INTO article (title, body, summary, author, created_at, updated_at)
+                               VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
                                RETURNING id, created_at, updated_at",
-            &[&title, &body, &author.id],
+            &[&title, &body, &summary, &author.id],
         )?;
         rows.iter()
             .next()
@@ -34,24 +129,82 @@ impl Article {
                     id: row.get(0),
                     title: title.to_string(),
                     body: body.to_string(),
+                    summary: summary.map(|s| s.to_string()),
+                    slug: None,
+                    draft: false,
+                    pinned: false,
+                    featured: false,
                     created_at: row.get(1),
                     updated_at: row.get(2),
                     author: Person {
                         id: author.id,
                         name: author.name.to_string(),
                     },
+                    rendered: None,
                 })
             })
             .chain_err(|| "failed to get article creation result")
     }
 
-    pub fn update(conn: &GenericConnection, id: i32, title: &str, body: &str) -> Result<()> {
-        match conn.execute(
-            "UPDATE article SET title = $1, body = $2, updated_at = CURRENT_TIMESTAMP WHERE id = $3",
-            &[&title, &body, &id],
-        )? {
-            1 => Ok(()),
-            _ => Err("no article updated".into()),
+    /// Inserts an imported article and its tags unless an article with the same slug
+    /// already exists. Returns the id of the new article.
+    pub fn import(
+        conn: &GenericConnection,
+        article: &ImportedArticle,
+        author: &Person,
+    ) -> Result<Option<i32>> {
+        let rows = conn.query(
+            "INSERT INTO article (title, body, summary, slug, draft, author, created_at, updated_at)
+                               VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                               ON CONFLICT (slug) DO NOTHING
+                               RETURNING id",
+            &[
+                &article.title,
+                &article.body,
+                &article.summary,
+                &article.slug,
+                &article.draft,
+                &author.id,
+                &article.created_at,
+                &article.updated_at,
+            ],
+        )?;
+        match rows.iter().next() {
+            Some(row) => {
+                let id = row.get(0);
+                Tag::set_for_article(conn, id, &article.tags)?;
+                Ok(Some(id))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn find_id_by_slug(conn: &GenericConnection, slug: &str) -> Result<Option<i32>> {
+        let rows = conn.query("SELECT id FROM article WHERE slug = $1", &[&slug])?;
+        Ok(rows.iter().next().map(|row| row.get(0)))
+    }
+
+    /// Updates an article unless it has been updated since `updated_at`, i.e. since
+    /// it was loaded into the editor. Returns whether it was updated, so that the
+    /// changes of another editor are not silently overwritten.
+    pub fn update(
+        conn: &GenericConnection,
+        id: i32,
+        title: &str,
+        body: &str,
+        summary: Option<&str>,
+        updated_at: &DateTime<Local>,
+    ) -> Result<bool> {
+        let updated = conn.execute(
+            "UPDATE article SET title = $1, body = $2, summary = $3, updated_at = CURRENT_TIMESTAMP,
+                               body_html = NULL, summary_html = NULL
+                               WHERE id = $4 AND updated_at = $5",
+            &[&title, &body, &summary, &id, updated_at],
+        )?;
+        match (updated, Article::revision(conn, id)?) {
+            (1, _) => Ok(true),
+            (_, Some(_)) => Ok(false),
+            (_, None) => Err("no article updated".into()),
         }
     }
 
@@ -63,51 +216,181 @@ impl Article {
     }
 
     pub fn get(conn: &GenericConnection, id: i32) -> Result<Article> {
-        let rows = conn.query(
-            "SELECT a.id, a.title, a.body, a.created_at, a.updated_at, p.id, p.name
-                               FROM article a JOIN person p ON p.id = a.author
-                               WHERE a.id = $1",
-            &[&id],
-        )?;
+        let rows = conn.query(&format!("{} WHERE a.id = $1", SELECT_ARTICLE), &[&id])?;
         rows.iter()
             .next()
-            .and_then(|row| {
-                Some(Article {
-                    id: row.get(0),
-                    title: row.get(1),
-                    body: row.get(2),
-                    created_at: row.get(3),
-                    updated_at: row.get(4),
-                    author: Person {
-                        id: row.get(5),
-                        name: row.get(6),
-                    },
-                })
-            })
+            .map(|row| from_row(&row))
             .chain_err(|| "person does not exist")
     }
 
     pub fn list(conn: &GenericConnection) -> Result<Vec<Article>> {
         let rows = conn.query(
-            "SELECT a.id, a.title, a.body, a.created_at, a.updated_at, p.id, p.name
-                               FROM article a JOIN person p ON p.id = a.author
-                               ORDER BY a.updated_at DESC",
+            &format!("{} ORDER BY a.updated_at DESC", SELECT_ARTICLE),
             &[],
         )?;
-        let articles = rows.iter()
-            .map(|row| Article {
-                id: row.get(0),
-                title: row.get(1),
-                body: row.get(2),
-                created_at: row.get(3),
-                updated_at: row.get(4),
-                author: Person {
-                    id: row.get(5),
-                    name: row.get(6),
-                },
-            })
-            .collect();
-        Ok(articles)
+        Ok(rows.iter().map(|row| from_row(&row)).collect())
+    }
+
+    /// Lists the articles shown on the public site, i.e. all but drafts, in the
+    /// order of the index: pinned ones first, then newest first.
+    pub fn list_published(conn: &GenericConnection) -> Result<Vec<Article>> {
+        let rows = conn.query(
+            &format!("{} WHERE NOT a.draft ORDER BY {}", SELECT_ARTICLE, LISTING_ORDER),
+            &[],
+        )?;
+        Ok(rows.iter().map(|row| from_row(&row)).collect())
+    }
+
+    /// Caches the rendered HTML of an article unless it has been updated since
+    /// `updated_at`, when the HTML would already be stale.
+    pub fn store_rendered(
+        conn: &GenericConnection,
+        id: i32,
+        updated_at: &DateTime<Local>,
+        rendered: &Rendered,
+    ) -> Result<()> {
+        conn.execute(
+            "UPDATE article SET body_html = $1, summary_html = $2
+                               WHERE id = $3 AND updated_at = $4",
+            &[&rendered.body, &rendered.summary, &id, updated_at],
+        )?;
+        Ok(())
+    }
+
+    /// Drops the rendered HTML of all articles, e.g. after the Markdown renderer
+    /// configuration has changed.
+    pub fn clear_rendered(conn: &GenericConnection) -> Result<u64> {
+        Ok(conn.execute(
+            "UPDATE article SET body_html = NULL, summary_html = NULL",
+            &[],
+        )?)
+    }
+
+    /// Lists one page of the published articles in the order of the index.
+    pub fn list_published_page(
+        conn: &GenericConnection,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<Article>> {
+        let rows = conn.query(
+            &format!(
+                "{} WHERE NOT a.draft ORDER BY {} OFFSET $1 LIMIT $2",
+                SELECT_ARTICLE, LISTING_ORDER
+            ),
+            &[&offset, &limit],
+        )?;
+        Ok(rows.iter().map(|row| from_row(&row)).collect())
+    }
+
+    /// Lists up to `limit` published featured articles, newest first.
+    pub fn list_featured(conn: &GenericConnection, limit: i64) -> Result<Vec<ArticleLink>> {
+        let rows = conn.query(
+            "SELECT id, title, created_at FROM article
+                               WHERE featured AND NOT draft
+                               ORDER BY created_at DESC, id DESC LIMIT $1",
+            &[&limit],
+        )?;
+        Ok(rows.iter().map(|row| link_from_row(&row)).collect())
+    }
+
+    /// Pins an article to the top of the index or marks it featured, or undoes
+    /// either. This does not count as an update of the article.
+    pub fn promote(conn: &GenericConnection, id: i32, pinned: bool, featured: bool) -> Result<()> {
+        match conn.execute(
+            "UPDATE article SET pinned = $1, featured = $2, promoted_at = CURRENT_TIMESTAMP
+                               WHERE id = $3",
+            &[&pinned, &featured, &id],
+        )? {
+            1 => Ok(()),
+            _ => Err("no article updated".into()),
+        }
+    }
+
+    /// Lists the published articles of a series in order.
+    pub fn list_published_in_series(conn: &GenericConnection, series: i32) -> Result<Vec<Article>> {
+        let rows = conn.query(
+            &format!(
+                "{} JOIN series_article sa ON sa.article = a.id
+                               WHERE sa.series = $1 AND NOT a.draft ORDER BY sa.position",
+                SELECT_ARTICLE
+            ),
+            &[&series],
+        )?;
+        Ok(rows.iter().map(|row| from_row(&row)).collect())
+    }
+
+    /// Returns when an article was last updated and whether it is a draft without
+    /// loading its body.
+    pub fn revision(conn: &GenericConnection, id: i32) -> Result<Option<(DateTime<Local>, bool)>> {
+        let rows = conn.query("SELECT updated_at, draft FROM article WHERE id = $1", &[&id])?;
+        Ok(rows.iter().next().map(|row| (row.get(0), row.get(1))))
+    }
+
+    /// Finds the published articles written just before and after an article and
+    /// up to `related` articles related to it.
+    pub fn navigation(
+        conn: &GenericConnection,
+        article: &Article,
+        related: i64,
+    ) -> Result<Navigation> {
+        let previous = conn.query(
+            "SELECT id, title, created_at FROM article
+                               WHERE NOT draft AND (created_at, id) < ($1, $2)
+                               ORDER BY created_at DESC, id DESC LIMIT 1",
+            &[&article.created_at, &article.id],
+        )?;
+        let next = conn.query(
+            "SELECT id, title, created_at FROM article
+                               WHERE NOT draft AND (created_at, id) > ($1, $2)
+                               ORDER BY created_at, id LIMIT 1",
+            &[&article.created_at, &article.id],
+        )?;
+        Ok(Navigation {
+            previous: previous.iter().next().map(|row| link_from_row(&row)),
+            next: next.iter().next().map(|row| link_from_row(&row)),
+            related: Article::related(conn, article, related)?,
+            series: Series::for_article(conn, article.id)?,
+        })
+    }
+
+    /// Lists published articles related to an article: those sharing the most tags
+    /// first, then those with the most similar titles by trigrams. Only candidates
+    /// found through the indexes on tags and titles are ranked.
+    pub fn related(conn: &GenericConnection, article: &Article, limit: i64) -> Result<Vec<ArticleLink>> {
+        let rows = conn.query(
+            "WITH candidate AS (
+                                   SELECT other.article AS id, count(*) AS shared_tags
+                                     FROM article_tag own
+                                     JOIN article_tag other
+                                       ON other.tag = own.tag AND other.article <> own.article
+                                    WHERE own.article = $1
+                                    GROUP BY other.article
+                                   UNION ALL
+                                   SELECT id, 0 FROM article WHERE title % $2 AND id <> $1
+                               )
+                               SELECT a.id, a.title, a.created_at
+                                 FROM (SELECT id, max(shared_tags) AS shared_tags
+                                         FROM candidate GROUP BY id) c
+                                 JOIN article a ON a.id = c.id
+                                WHERE NOT a.draft
+                                ORDER BY c.shared_tags DESC, similarity(a.title, $2) DESC,
+                                         a.created_at DESC
+                                LIMIT $3",
+            &[&article.id, &article.title, &limit],
+        )?;
+        Ok(rows.iter().map(|row| link_from_row(&row)).collect())
+    }
+
+    /// Returns the number of published articles and the latest time one of them was
+    /// updated or promoted, which together change whenever the public list of
+    /// articles does.
+    pub fn published_revision(conn: &GenericConnection) -> Result<(i64, Option<DateTime<Local>>)> {
+        let rows = conn.query(
+            "SELECT count(*), max(greatest(updated_at, promoted_at)) FROM article WHERE NOT draft",
+            &[],
+        )?;
+        let row = rows.get(0);
+        Ok((row.get(0), row.get(1)))
     }
 }
 
@@ -117,6 +400,8 @@ mod tests {
     use db::person::Person;
     use db::testutil;
 
+    use chrono::Duration;
+
     #[test]
     fn create() {
         testutil::with_db(|conn| {
@@ -124,7 +409,7 @@ mod tests {
                 id: 1,
                 name: "system".to_owned(),
             };
-            let article = Article::create(conn, "title", "body", &author).unwrap();
+            let article = Article::create(conn, "title", "body", None, &author).unwrap();
             assert!(article.id > 0, "article id is present");
         });
     }
@@ -136,20 +421,40 @@ mod tests {
                 conn,
                 "title",
                 "body",
+                None,
                 &Person {
                     id: 1,
                     name: "system".to_owned(),
                 },
             ).unwrap();
-            Article::update(conn, article.id, "title2", "body2").unwrap();
+            let updated = Article::update(
+                conn,
+                article.id,
+                "title2",
+                "body2",
+                Some("summary2"),
+                &article.updated_at,
+            ).unwrap();
+            assert!(updated);
+            // A save based on an older revision is rejected.
+            let stale = article.updated_at - Duration::seconds(1);
+            let updated = Article::update(conn, article.id, "title3", "body3", None, &stale).unwrap();
+            assert!(!updated);
             let result = conn.query(
-                "SELECT title, body FROM article WHERE id = $1",
+                "SELECT title, body, summary FROM article WHERE id = $1",
                 &[&article.id],
             ).unwrap()
                 .iter()
                 .next()
-                .map(|row| (row.get(0), row.get(1)));
-            assert_eq!(result, Some(("title2".to_owned(), "body2".to_owned())));
+                .map(|row| (row.get(0), row.get(1), row.get(2)));
+            assert_eq!(
+                result,
+                Some((
+                    "title2".to_owned(),
+                    "body2".to_owned(),
+                    Some("summary2".to_owned())
+                ))
+            );
         });
     }
 
@@ -177,6 +482,11 @@ mod tests {
                 id: 1,
                 title: "title".to_owned(),
                 body: "body".to_owned(),
+                summary: None,
+                slug: None,
+                draft: false,
+                pinned: false,
+                featured: false,
                 created_at: "2000-1-2T03:04:05.006+09:00"
                     .parse::<DateTime<Local>>()
                     .unwrap(),
@@ -187,6 +497,7 @@ mod tests {
                     id: 1,
                     name: "system".to_owned(),
                 },
+                rendered: None,
             };
             let actual = Article::get(conn, 1).unwrap();
             assert_eq!(expected, actual);
@@ -206,4 +517,192 @@ mod tests {
             assert_eq!(articles.len(), 1);
         });
     }
+
+    #[test]
+    fn list_published() {
+        testutil::with_db(|conn| {
+            conn.execute("DELETE FROM article", &[]).unwrap();
+            conn.execute(
+                "INSERT INTO article (title, body, draft, author, created_at, updated_at)
+                          VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)",
+                &[&"title", &"body", &true, &1],
+            ).unwrap();
+            let articles = Article::list_published(conn).unwrap();
+            assert!(articles.is_empty());
+        });
+    }
+
+    #[test]
+    fn list_published_page() {
+        testutil::with_db(|conn| {
+            conn.execute("DELETE FROM article", &[]).unwrap();
+            // Updating an article does not move it up the index.
+            conn.execute(
+                "INSERT INTO article (title, body, author, created_at, updated_at)
+                          SELECT 'title' || n, 'body', 1,
+                                 CURRENT_TIMESTAMP + n * INTERVAL '1 minute',
+                                 CURRENT_TIMESTAMP - n * INTERVAL '1 minute'
+                            FROM generate_series(1, 5) AS n",
+                &[],
+            ).unwrap();
+            let titles = |offset, limit| {
+                Article::list_published_page(conn, offset, limit)
+                    .unwrap()
+                    .into_iter()
+                    .map(|article| article.title)
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(titles(0, 2), vec!["title5", "title4"]);
+            assert_eq!(titles(4, 2), vec!["title1"]);
+
+            let id = |title: &str| -> i32 {
+                conn.query("SELECT id FROM article WHERE title = $1", &[&title])
+                    .unwrap()
+                    .get(0)
+                    .get(0)
+            };
+            Article::promote(conn, id("title2"), true, false).unwrap();
+            assert_eq!(titles(0, 2), vec!["title2", "title5"]);
+            assert_eq!(titles(4, 2), vec!["title1"]);
+        });
+    }
+
+    #[test]
+    fn list_featured() {
+        testutil::with_db(|conn| {
+            conn.execute("DELETE FROM article", &[]).unwrap();
+            conn.execute(
+                "INSERT INTO article (id, title, body, draft, featured, author, created_at, updated_at)
+                          SELECT id, title, 'body', draft, featured, 1, yesterday, yesterday
+                            FROM (VALUES (1, 'featured', false, true),
+                                         (2, 'draft', true, true),
+                                         (3, 'plain', false, false))
+                                 AS a (id, title, draft, featured),
+                                 (SELECT CURRENT_TIMESTAMP - INTERVAL '1 day' AS yesterday) t",
+                &[],
+            ).unwrap();
+            let (_, before) = Article::published_revision(conn).unwrap();
+            Article::promote(conn, 3, false, true).unwrap();
+            let ids: Vec<i32> = Article::list_featured(conn, 5)
+                .unwrap()
+                .into_iter()
+                .map(|link| link.id)
+                .collect();
+            assert_eq!(ids, vec![3, 1]);
+            // Promoting an article changes the index without updating the article.
+            let (_, after) = Article::published_revision(conn).unwrap();
+            assert!(after > before);
+            assert_eq!(Article::get(conn, 3).unwrap().updated_at, before.unwrap());
+        });
+    }
+
+    #[test]
+    fn rendered() {
+        testutil::with_db(|conn| {
+            let author = Person {
+                id: 1,
+                name: "system".to_owned(),
+            };
+            let article = Article::create(conn, "title", "body", None, &author).unwrap();
+            let rendered = Rendered {
+                body: "<p>body</p>".to_owned(),
+                summary: None,
+            };
+            Article::store_rendered(conn, article.id, &article.updated_at, &rendered).unwrap();
+            assert_eq!(Article::get(conn, article.id).unwrap().rendered, Some(rendered.clone()));
+            Article::update(conn, article.id, "title", "body2", None, &article.updated_at).unwrap();
+            assert_eq!(Article::get(conn, article.id).unwrap().rendered, None);
+            // HTML rendered from an older revision is not stored.
+            let stale = article.updated_at - Duration::seconds(1);
+            Article::store_rendered(conn, article.id, &stale, &rendered).unwrap();
+            assert_eq!(Article::get(conn, article.id).unwrap().rendered, None);
+        });
+    }
+
+    #[test]
+    fn revisions() {
+        testutil::with_db(|conn| {
+            conn.execute("DELETE FROM article", &[]).unwrap();
+            assert_eq!(Article::published_revision(conn).unwrap(), (0, None));
+            conn.execute(
+                "INSERT INTO article (id, title, body, draft, author, created_at, updated_at)
+                          VALUES (1, 'a', 'body', false, 1, CURRENT_TIMESTAMP, '2017-12-01 12:00:00+09'),
+                                 (2, 'b', 'body', true, 1, CURRENT_TIMESTAMP, '2017-12-02 12:00:00+09')",
+                &[],
+            ).unwrap();
+            let date = "2017-12-01T12:00:00+09:00"
+                .parse::<DateTime<Local>>()
+                .unwrap();
+            assert_eq!(Article::published_revision(conn).unwrap(), (1, Some(date)));
+            assert_eq!(Article::revision(conn, 1).unwrap(), Some((date, false)));
+            assert_eq!(Article::revision(conn, 3).unwrap(), None);
+        });
+    }
+
+    #[test]
+    fn navigation() {
+        testutil::with_db(|conn| {
+            conn.execute("DELETE FROM article", &[]).unwrap();
+            conn.execute(
+                "INSERT INTO article (id, title, body, draft, author, created_at, updated_at)
+                          VALUES (1, 'Rust で web アプリ', 'body', false, 1, '2017-12-01 12:00:00+09', CURRENT_TIMESTAMP),
+                                 (2, 'Rust で web アプリ その2', 'body', false, 1, '2017-12-02 12:00:00+09', CURRENT_TIMESTAMP),
+                                 (3, '下書き', 'body', true, 1, '2017-12-03 12:00:00+09', CURRENT_TIMESTAMP),
+                                 (4, '料理', 'body', false, 1, '2017-12-04 12:00:00+09', CURRENT_TIMESTAMP),
+                                 (5, '旅行', 'body', false, 1, '2017-12-04 12:00:00+09', CURRENT_TIMESTAMP)",
+                &[],
+            ).unwrap();
+            Tag::set_for_article(conn, 2, &["rust".to_owned(), "web".to_owned()]).unwrap();
+            Tag::set_for_article(conn, 3, &["rust".to_owned(), "web".to_owned()]).unwrap();
+            Tag::set_for_article(conn, 4, &["web".to_owned()]).unwrap();
+            Tag::set_for_article(conn, 5, &["rust".to_owned(), "web".to_owned()]).unwrap();
+            let ids = |links: Vec<ArticleLink>| links.into_iter().map(|link| link.id).collect::<Vec<_>>();
+
+            let article = Article::get(conn, 2).unwrap();
+            let navigation = Article::navigation(conn, &article, 3).unwrap();
+            assert_eq!(navigation.previous.map(|link| link.id), Some(1));
+            // The draft in between is skipped.
+            assert_eq!(navigation.next.map(|link| link.id), Some(4));
+            // Most shared tags first; article 1 has no tags but a similar title.
+            assert_eq!(ids(navigation.related), vec![5, 4, 1]);
+
+            // Articles written at the same time are ordered by id.
+            let article = Article::get(conn, 4).unwrap();
+            let navigation = Article::navigation(conn, &article, 3).unwrap();
+            assert_eq!(navigation.next.map(|link| link.id), Some(5));
+            let article = Article::get(conn, 5).unwrap();
+            let navigation = Article::navigation(conn, &article, 3).unwrap();
+            assert_eq!(navigation.previous.map(|link| link.id), Some(4));
+            assert_eq!(navigation.next, None);
+        });
+    }
+
+    #[test]
+    fn import() {
+        testutil::with_db(|conn| {
+            let author = Person {
+                id: 1,
+                name: "system".to_owned(),
+            };
+            let date = "2000-1-2T03:04:05.006+09:00"
+                .parse::<DateTime<Local>>()
+                .unwrap();
+            let imported = ImportedArticle {
+                title: "title".to_owned(),
+                body: "body".to_owned(),
+                summary: None,
+                slug: "imported-slug".to_owned(),
+                draft: false,
+                tags: vec!["tag".to_owned()],
+                created_at: date,
+                updated_at: date,
+            };
+            let id = Article::import(conn, &imported, &author).unwrap().unwrap();
+            assert_eq!(Article::import(conn, &imported, &author).unwrap(), None);
+            assert_eq!(Article::find_id_by_slug(conn, "imported-slug").unwrap(), Some(id));
+            let article = Article::get(conn, id).unwrap();
+            assert_eq!(article.slug, Some("imported-slug".to_owned()));
+            assert_eq!(article.created_at, date);
+        });
+    }
 }
@@ -0,0 +1,217 @@
+use errors::*;
+
+use chrono::{DateTime, Local};
+use postgres::GenericConnection;
+use postgres::rows::Row;
+
+/// An action recorded in the audit log.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Login,
+    LoginFailed,
+    Logout,
+    CreateArticle,
+    UpdateArticle,
+    DeleteArticle,
+    PromoteArticle,
+    UpdateSettings,
+    CreateSeries,
+    UpdateSeries,
+    DeleteSeries,
+}
+
+impl Action {
+    pub const ALL: &'static [Action] = &[
+        Action::Login,
+        Action::LoginFailed,
+        Action::Logout,
+        Action::CreateArticle,
+        Action::UpdateArticle,
+        Action::DeleteArticle,
+        Action::PromoteArticle,
+        Action::UpdateSettings,
+        Action::CreateSeries,
+        Action::UpdateSeries,
+        Action::DeleteSeries,
+    ];
+
+    /// The name stored in the log, e.g. `article_update`.
+    pub fn code(&self) -> &'static str {
+        match *self {
+            Action::Login => "login",
+            Action::LoginFailed => "login_failed",
+            Action::Logout => "logout",
+            Action::CreateArticle => "article_create",
+            Action::UpdateArticle => "article_update",
+            Action::DeleteArticle => "article_delete",
+            Action::PromoteArticle => "article_promote",
+            Action::UpdateSettings => "settings_update",
+            Action::CreateSeries => "series_create",
+            Action::UpdateSeries => "series_update",
+            Action::DeleteSeries => "series_delete",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Action> {
+        Action::ALL
+            .iter()
+            .find(|action| action.code() == code)
+            .cloned()
+    }
+}
+
+/// One recorded action.
+#[derive(Debug, PartialEq)]
+pub struct Entry {
+    pub id: i32,
+    pub action: Action,
+    /// The name of the person who did it, empty when nobody was logged in.
+    pub actor: String,
+    pub target: String,
+    pub target_id: Option<i32>,
+    pub ip: Option<String>,
+    pub created_at: DateTime<Local>,
+}
+
+/// Conditions on the entries to list. `None` matches any.
+#[derive(Debug, Default)]
+pub struct Filter<'a> {
+    pub action: Option<Action>,
+    pub actor: Option<&'a str>,
+}
+
+const FILTER: &'static str = "($1::text IS NULL OR action = $1)
+                               AND ($2::text IS NULL OR actor_name = $2)";
+
+fn from_row(row: &Row) -> Result<Entry> {
+    let code: String = row.get(1);
+    let action = Action::from_code(&code).chain_err(|| format!("unknown action: {}", code))?;
+    Ok(Entry {
+        id: row.get(0),
+        action: action,
+        actor: row.get(2),
+        target: row.get(3),
+        target_id: row.get(4),
+        ip: row.get(5),
+        created_at: row.get(6),
+    })
+}
+
+impl Entry {
+    /// Records an action by `actor`, or by nobody logged in, done to `target` and
+    /// requested from `ip`.
+    pub fn record(
+        conn: &GenericConnection,
+        action: Action,
+        actor: Option<i32>,
+        target: &str,
+        target_id: Option<i32>,
+        ip: Option<&str>,
+    ) -> Result<()> {
+        conn.execute(
+            "INSERT INTO audit_log (action, actor, actor_name, target, target_id, ip, created_at)
+                               VALUES ($1, $2,
+                                       coalesce((SELECT name FROM person WHERE id = $2), ''),
+                                       $3, $4, $5, CURRENT_TIMESTAMP)",
+            &[&action.code(), &actor, &target, &target_id, &ip],
+        )?;
+        Ok(())
+    }
+
+    /// Lists one page of the entries matching `filter`, newest first.
+    pub fn list(
+        conn: &GenericConnection,
+        filter: &Filter,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<Entry>> {
+        let rows = conn.query(
+            &format!(
+                "SELECT id, action, actor_name, target, target_id, ip, created_at FROM audit_log
+                                   WHERE {} ORDER BY created_at DESC, id DESC OFFSET $3 LIMIT $4",
+                FILTER
+            ),
+            &[
+                &filter.action.map(|action| action.code()),
+                &filter.actor,
+                &offset,
+                &limit,
+            ],
+        )?;
+        rows.iter().map(|row| from_row(&row)).collect()
+    }
+
+    pub fn count(conn: &GenericConnection, filter: &Filter) -> Result<i64> {
+        let rows = conn.query(
+            &format!("SELECT count(*) FROM audit_log WHERE {}", FILTER),
+            &[&filter.action.map(|action| action.code()), &filter.actor],
+        )?;
+        rows.iter()
+            .next()
+            .map(|row| row.get(0))
+            .chain_err(|| "failed to count audit log entries")
+    }
+
+    /// The names of everyone who has done something, to filter the entries by.
+    pub fn actors(conn: &GenericConnection) -> Result<Vec<String>> {
+        let rows = conn.query(
+            "SELECT DISTINCT actor_name FROM audit_log WHERE actor_name <> ''
+                               ORDER BY actor_name",
+            &[],
+        )?;
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use db::testutil;
+
+    #[test]
+    fn action_codes() {
+        for action in Action::ALL {
+            assert_eq!(Action::from_code(action.code()), Some(*action));
+        }
+        assert_eq!(Action::from_code("unknown"), None);
+    }
+
+    #[test]
+    fn record_and_list() {
+        testutil::with_db(|conn| {
+            conn.execute("DELETE FROM audit_log", &[]).unwrap();
+            let ip = Some("192.0.2.1");
+            Entry::record(conn, Action::LoginFailed, None, "system", None, ip).unwrap();
+            Entry::record(conn, Action::Login, Some(1), "", None, ip).unwrap();
+            Entry::record(conn, Action::DeleteArticle, Some(1), "title", Some(2), None).unwrap();
+
+            let all = Filter::default();
+            assert_eq!(Entry::count(conn, &all).unwrap(), 3);
+            let entries = Entry::list(conn, &all, 0, 2).unwrap();
+            assert_eq!(entries.len(), 2);
+            // Entries of one transaction share their time, so the latest id comes first.
+            assert_eq!(entries[0].action, Action::DeleteArticle);
+            assert_eq!(entries[0].actor, "system");
+            assert_eq!(entries[0].target, "title");
+            assert_eq!(entries[0].target_id, Some(2));
+            assert_eq!(entries[0].ip, None);
+            assert_eq!(Entry::list(conn, &all, 2, 2).unwrap()[0].action, Action::LoginFailed);
+
+            let failed = Filter {
+                action: Some(Action::LoginFailed),
+                actor: None,
+            };
+            let entries = Entry::list(conn, &failed, 0, 10).unwrap();
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].actor, "");
+            assert_eq!(entries[0].ip, Some("192.0.2.1".to_owned()));
+
+            let by_system = Filter {
+                action: None,
+                actor: Some("system"),
+            };
+            assert_eq!(Entry::count(conn, &by_system).unwrap(), 2);
+            assert_eq!(Entry::actors(conn).unwrap(), vec!["system".to_owned()]);
+        });
+    }
+}
@@ -0,0 +1,107 @@
+use errors::*;
+
+use chrono::{DateTime, Local};
+use postgres::GenericConnection;
+
+/// The contents of the article editor saved while it is open, so that they survive
+/// an expired login or a lost connection. Each person has one per article and one
+/// for a new article, identified by `article` being `None`.
+#[derive(Debug, PartialEq)]
+pub struct Autosave {
+    pub title: String,
+    pub body: String,
+    pub summary: String,
+    pub saved_at: DateTime<Local>,
+}
+
+impl Autosave {
+    /// Saves the editor contents, replacing those saved before. Returns when they
+    /// were saved.
+    pub fn save(
+        conn: &GenericConnection,
+        person: i32,
+        article: Option<i32>,
+        title: &str,
+        body: &str,
+        summary: &str,
+    ) -> Result<DateTime<Local>> {
+        let rows = conn.query(
+            "INSERT INTO article_autosave (person, article, title, body, summary, saved_at)
+                               VALUES ($1, $2, $3, $4, $5, CURRENT_TIMESTAMP)
+                               ON CONFLICT (person, coalesce(article, 0))
+                               DO UPDATE SET title = EXCLUDED.title, body = EXCLUDED.body,
+                                             summary = EXCLUDED.summary,
+                                             saved_at = EXCLUDED.saved_at
+                               RETURNING saved_at",
+            &[&person, &article, &title, &body, &summary],
+        )?;
+        rows.iter()
+            .next()
+            .map(|row| row.get(0))
+            .chain_err(|| "failed to get autosave result")
+    }
+
+    pub fn find(
+        conn: &GenericConnection,
+        person: i32,
+        article: Option<i32>,
+    ) -> Result<Option<Autosave>> {
+        let rows = conn.query(
+            "SELECT title, body, summary, saved_at FROM article_autosave
+                               WHERE person = $1 AND article IS NOT DISTINCT FROM $2",
+            &[&person, &article],
+        )?;
+        Ok(rows.iter().next().map(|row| Autosave {
+            title: row.get(0),
+            body: row.get(1),
+            summary: row.get(2),
+            saved_at: row.get(3),
+        }))
+    }
+
+    /// Deletes the saved contents, e.g. once the article has been saved.
+    pub fn discard(conn: &GenericConnection, person: i32, article: Option<i32>) -> Result<()> {
+        conn.execute(
+            "DELETE FROM article_autosave
+                               WHERE person = $1 AND article IS NOT DISTINCT FROM $2",
+            &[&person, &article],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use db::testutil;
+
+    #[test]
+    fn save_and_discard() {
+        testutil::with_db(|conn| {
+            conn.execute("DELETE FROM article_autosave", &[]).unwrap();
+            conn.execute(
+                "INSERT INTO article (id, title, body, author, created_at, updated_at)
+                          VALUES (1, 'title', 'body', 1, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+                          ON CONFLICT (id) DO NOTHING",
+                &[],
+            ).unwrap();
+            Autosave::save(conn, 1, None, "new", "draft", "").unwrap();
+            Autosave::save(conn, 1, Some(1), "title", "body 1", "").unwrap();
+            let saved_at = Autosave::save(conn, 1, Some(1), "title", "body 2", "").unwrap();
+            assert_eq!(
+                Autosave::find(conn, 1, Some(1)).unwrap(),
+                Some(Autosave {
+                    title: "title".to_owned(),
+                    body: "body 2".to_owned(),
+                    summary: String::new(),
+                    saved_at: saved_at,
+                })
+            );
+
+            Autosave::discard(conn, 1, Some(1)).unwrap();
+            assert_eq!(Autosave::find(conn, 1, Some(1)).unwrap(), None);
+            let new = Autosave::find(conn, 1, None).unwrap().unwrap();
+            assert_eq!(new.body, "draft");
+        });
+    }
+}
@@ -1,5 +1,10 @@
 pub mod article;
+pub mod audit;
+pub mod autosave;
 pub mod person;
+pub mod series;
+pub mod setting;
+pub mod tag;
 
 #[cfg(test)]
 pub mod testutil;
@@ -31,6 +36,12 @@ pub fn init_pool(database_url: &str) -> Result<Pool> {
     Ok(pool)
 }
 
+impl From<r2d2::PooledConnection<PostgresConnectionManager>> for Connection {
+    fn from(conn: r2d2::PooledConnection<PostgresConnectionManager>) -> Connection {
+        Connection(conn)
+    }
+}
+
 impl Deref for Connection {
     type Target = postgres::Connection;
 
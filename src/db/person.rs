@@ -3,6 +3,11 @@ use super::hash_password;
 
 use postgres::GenericConnection;
 
+/// Password hash stored for persons created without a password, e.g. by an import.
+/// No SHA-256 hex digest matches it, so such persons cannot log in.
+pub const LOCKED_PASSWORD: &'static str =
+    "****************************************************************";
+
 #[derive(Debug, PartialEq)]
 pub struct Person {
     pub id: i32,
@@ -39,6 +44,28 @@ impl Person {
             })
             .chain_err(|| "person does not exist")
     }
+
+    /// Returns the person with the given name, creating one that cannot log in if missing.
+    pub fn get_or_create_locked(conn: &GenericConnection, name: &str) -> Result<Person> {
+        conn.execute(
+            "INSERT INTO person (name, password) VALUES ($1, $2) ON CONFLICT (name) DO NOTHING",
+            &[&name, &LOCKED_PASSWORD],
+        )?;
+        Person::get_by_name(conn, name)
+    }
+
+    pub fn get_by_name(conn: &GenericConnection, name: &str) -> Result<Person> {
+        let rows = conn.query("SELECT id FROM person WHERE name = $1", &[&name])?;
+        rows.iter()
+            .next()
+            .and_then(|row| {
+                Some(Person {
+                    id: row.get(0),
+                    name: name.to_string(),
+                })
+            })
+            .chain_err(|| "person does not exist")
+    }
 }
 
 #[cfg(test)]
@@ -73,4 +100,21 @@ mod tests {
             );
         })
     }
+
+    #[test]
+    fn get_by_name() {
+        testutil::with_db(|conn| {
+            let person = Person::get_by_name(conn, "system").unwrap();
+            assert_eq!(person.id, 1);
+        })
+    }
+
+    #[test]
+    fn get_or_create_locked() {
+        testutil::with_db(|conn| {
+            let person = Person::get_or_create_locked(conn, "locked").unwrap();
+            assert_eq!(Person::get_or_create_locked(conn, "locked").unwrap(), person);
+            assert!(Person::find(conn, "locked", "").is_err());
+        })
+    }
 }
@@ -0,0 +1,252 @@
+use errors::*;
+use super::article::{link_from_row, ArticleLink};
+
+use chrono::{DateTime, Local};
+use postgres::GenericConnection;
+use postgres::rows::Row;
+
+/// A group of articles meant to be read in order, e.g. a multi-part tutorial. An
+/// article belongs to one series at most.
+#[derive(Debug, PartialEq)]
+pub struct Series {
+    pub id: i32,
+    pub slug: String,
+    pub title: String,
+    pub description: String,
+    pub updated_at: DateTime<Local>,
+}
+
+/// A series with its published articles in order.
+#[derive(Debug, PartialEq)]
+pub struct SeriesParts {
+    pub series: Series,
+    pub parts: Vec<ArticleLink>,
+}
+
+impl SeriesParts {
+    /// The 1-based position of an article among the published parts.
+    pub fn position(&self, article_id: i32) -> Option<usize> {
+        self.parts
+            .iter()
+            .position(|part| part.id == article_id)
+            .map(|i| i + 1)
+    }
+}
+
+const SELECT_SERIES: &'static str =
+    "SELECT s.id, s.slug, s.title, s.description, s.updated_at FROM series s";
+
+fn from_row(row: &Row) -> Series {
+    Series {
+        id: row.get(0),
+        slug: row.get(1),
+        title: row.get(2),
+        description: row.get(3),
+        updated_at: row.get(4),
+    }
+}
+
+impl Series {
+    pub fn create(
+        conn: &GenericConnection,
+        slug: &str,
+        title: &str,
+        description: &str,
+    ) -> Result<Series> {
+        let rows = conn.query(
+            "INSERT INTO series (slug, title, description, created_at, updated_at)
+                               VALUES ($1, $2, $3, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+                               RETURNING id, slug, title, description, updated_at",
+            &[&slug, &title, &description],
+        )?;
+        rows.iter()
+            .next()
+            .map(|row| from_row(&row))
+            .chain_err(|| "failed to get series creation result")
+    }
+
+    pub fn update(
+        conn: &GenericConnection,
+        id: i32,
+        slug: &str,
+        title: &str,
+        description: &str,
+    ) -> Result<()> {
+        conn.execute(
+            "UPDATE series SET slug = $1, title = $2, description = $3,
+                               updated_at = CURRENT_TIMESTAMP
+                               WHERE id = $4",
+            &[&slug, &title, &description, &id],
+        )?;
+        Ok(())
+    }
+
+    /// Deletes a series. Its articles are kept.
+    pub fn delete(conn: &GenericConnection, id: i32) -> Result<()> {
+        conn.execute("DELETE FROM series WHERE id = $1", &[&id])?;
+        Ok(())
+    }
+
+    pub fn get(conn: &GenericConnection, id: i32) -> Result<Series> {
+        let rows = conn.query(&format!("{} WHERE s.id = $1", SELECT_SERIES), &[&id])?;
+        rows.iter()
+            .next()
+            .map(|row| from_row(&row))
+            .chain_err(|| "series does not exist")
+    }
+
+    pub fn find_by_slug(conn: &GenericConnection, slug: &str) -> Result<Option<Series>> {
+        let rows = conn.query(&format!("{} WHERE s.slug = $1", SELECT_SERIES), &[&slug])?;
+        Ok(rows.iter().next().map(|row| from_row(&row)))
+    }
+
+    pub fn list(conn: &GenericConnection) -> Result<Vec<Series>> {
+        let rows = conn.query(&format!("{} ORDER BY s.title", SELECT_SERIES), &[])?;
+        Ok(rows.iter().map(|row| from_row(&row)).collect())
+    }
+
+    /// Lists all articles of a series in order, each with whether it is a draft.
+    pub fn all_parts(conn: &GenericConnection, id: i32) -> Result<Vec<(ArticleLink, bool)>> {
+        let rows = conn.query(
+            "SELECT a.id, a.title, a.created_at, a.draft
+                               FROM series_article sa JOIN article a ON a.id = sa.article
+                               WHERE sa.series = $1
+                               ORDER BY sa.position",
+            &[&id],
+        )?;
+        Ok(rows.iter()
+            .map(|row| (link_from_row(&row), row.get(3)))
+            .collect())
+    }
+
+    /// Lists the published articles of a series in order.
+    pub fn parts(conn: &GenericConnection, id: i32) -> Result<Vec<ArticleLink>> {
+        let rows = conn.query(
+            "SELECT a.id, a.title, a.created_at
+                               FROM series_article sa JOIN article a ON a.id = sa.article
+                               WHERE sa.series = $1 AND NOT a.draft
+                               ORDER BY sa.position",
+            &[&id],
+        )?;
+        Ok(rows.iter().map(|row| link_from_row(&row)).collect())
+    }
+
+    /// Replaces the articles of a series with `article_ids` in order. Articles that
+    /// belong to another series are moved to this one.
+    pub fn set_articles(conn: &GenericConnection, id: i32, article_ids: &[i32]) -> Result<()> {
+        conn.execute("DELETE FROM series_article WHERE series = $1", &[&id])?;
+        for (position, article_id) in article_ids.iter().enumerate() {
+            conn.execute(
+                "INSERT INTO series_article (series, article, position) VALUES ($1, $2, $3)
+                               ON CONFLICT (article)
+                               DO UPDATE SET series = EXCLUDED.series, position = EXCLUDED.position",
+                &[&id, article_id, &(position as i32 + 1)],
+            )?;
+        }
+        conn.execute(
+            "UPDATE series SET updated_at = CURRENT_TIMESTAMP WHERE id = $1",
+            &[&id],
+        )?;
+        Ok(())
+    }
+
+    /// Finds the series an article belongs to, with the published parts.
+    pub fn for_article(conn: &GenericConnection, article_id: i32) -> Result<Option<SeriesParts>> {
+        let rows = conn.query(
+            &format!(
+                "{} JOIN series_article sa ON sa.series = s.id WHERE sa.article = $1",
+                SELECT_SERIES
+            ),
+            &[&article_id],
+        )?;
+        let series = match rows.iter().next() {
+            Some(row) => from_row(&row),
+            None => return Ok(None),
+        };
+        let parts = Series::parts(conn, series.id)?;
+        Ok(Some(SeriesParts {
+            series: series,
+            parts: parts,
+        }))
+    }
+
+    /// Returns the number of series and the latest time one was changed.
+    pub fn revision(conn: &GenericConnection) -> Result<(i64, Option<DateTime<Local>>)> {
+        let rows = conn.query("SELECT count(*), max(updated_at) FROM series", &[])?;
+        let row = rows.get(0);
+        Ok((row.get(0), row.get(1)))
+    }
+}
+
+/// Whether a slug is fit for a URL: lowercase ASCII letters, digits and hyphens.
+pub fn is_valid_slug(slug: &str) -> bool {
+    !slug.is_empty()
+        && slug.chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use db::testutil;
+
+    fn insert_articles(conn: &GenericConnection) {
+        conn.execute("DELETE FROM article", &[]).unwrap();
+        conn.execute(
+            "INSERT INTO article (id, title, body, draft, author, created_at, updated_at)
+                          VALUES (1, 'part 1', 'body', false, 1, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP),
+                                 (2, 'part 2', 'body', true, 1, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP),
+                                 (3, 'part 3', 'body', false, 1, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)",
+            &[],
+        ).unwrap();
+    }
+
+    #[test]
+    fn create_and_find() {
+        testutil::with_db(|conn| {
+            let series = Series::create(conn, "rust-web", "Rust で web", "").unwrap();
+            assert_eq!(Series::find_by_slug(conn, "rust-web").unwrap(), Some(series));
+            assert_eq!(Series::find_by_slug(conn, "none").unwrap(), None);
+        });
+    }
+
+    #[test]
+    fn parts() {
+        testutil::with_db(|conn| {
+            insert_articles(conn);
+            let series = Series::create(conn, "tutorial", "tutorial", "").unwrap();
+            Series::set_articles(conn, series.id, &[3, 2, 1]).unwrap();
+            let ids = |parts: Vec<ArticleLink>| parts.into_iter().map(|part| part.id).collect::<Vec<_>>();
+            assert_eq!(ids(Series::parts(conn, series.id).unwrap()), vec![3, 1]);
+            let drafts: Vec<bool> = Series::all_parts(conn, series.id)
+                .unwrap()
+                .into_iter()
+                .map(|(_, draft)| draft)
+                .collect();
+            assert_eq!(drafts, vec![false, true, false]);
+
+            let parts = Series::for_article(conn, 1).unwrap().unwrap();
+            assert_eq!(parts.series.slug, "tutorial");
+            assert_eq!(parts.position(1), Some(2));
+            assert_eq!(parts.position(2), None);
+
+            // Adding an article to another series moves it.
+            let other = Series::create(conn, "other", "other", "").unwrap();
+            Series::set_articles(conn, other.id, &[1]).unwrap();
+            assert_eq!(ids(Series::parts(conn, series.id).unwrap()), vec![3]);
+            assert_eq!(Series::for_article(conn, 1).unwrap().unwrap().series.id, other.id);
+
+            Series::delete(conn, other.id).unwrap();
+            assert_eq!(Series::for_article(conn, 1).unwrap(), None);
+        });
+    }
+
+    #[test]
+    fn valid_slug() {
+        assert!(is_valid_slug("rust-web-2"));
+        assert!(!is_valid_slug(""));
+        assert!(!is_valid_slug("Rust"));
+        assert!(!is_valid_slug("rust web"));
+        assert!(!is_valid_slug("ラスト"));
+    }
+}
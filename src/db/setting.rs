@@ -0,0 +1,242 @@
+//! Site settings editable in the admin, stored as name/value pairs.
+
+use errors::*;
+use locale::Locale;
+
+use chrono::{DateTime, FixedOffset, Local};
+use postgres::GenericConnection;
+
+/// A type that can be stored as the value of a setting.
+pub trait Value: Sized {
+    fn parse(s: &str) -> Result<Self>;
+    fn format(&self) -> String;
+}
+
+impl Value for String {
+    fn parse(s: &str) -> Result<String> {
+        Ok(s.to_owned())
+    }
+
+    fn format(&self) -> String {
+        self.clone()
+    }
+}
+
+impl Value for i64 {
+    fn parse(s: &str) -> Result<i64> {
+        s.trim()
+            .parse()
+            .chain_err(|| format!("invalid number: {}", s))
+    }
+
+    fn format(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// Time zones are UTC offsets such as `+09:00`.
+impl Value for FixedOffset {
+    fn parse(s: &str) -> Result<FixedOffset> {
+        let s = s.trim();
+        if s == "Z" || s.eq_ignore_ascii_case("UTC") {
+            return Ok(FixedOffset::east(0));
+        }
+        let invalid = || format!("invalid time zone: {}", s);
+        let sign = match s.chars().next() {
+            Some('+') => 1,
+            Some('-') => -1,
+            _ => bail!(invalid()),
+        };
+        let mut parts = s[1..].splitn(2, ':');
+        let hours: i32 = parts
+            .next()
+            .and_then(|hours| hours.parse().ok())
+            .chain_err(&invalid)?;
+        let minutes: i32 = match parts.next() {
+            Some(minutes) => minutes.parse().chain_err(&invalid)?,
+            None => 0,
+        };
+        if minutes >= 60 {
+            bail!(invalid());
+        }
+        FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).chain_err(&invalid)
+    }
+
+    fn format(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Value for Locale {
+    fn parse(s: &str) -> Result<Locale> {
+        Locale::from_code(s).chain_err(|| format!("unsupported locale: {}", s))
+    }
+
+    fn format(&self) -> String {
+        self.code().to_owned()
+    }
+}
+
+fn get_raw(conn: &GenericConnection, name: &str) -> Result<Option<String>> {
+    let rows = conn.query("SELECT value FROM setting WHERE name = $1", &[&name])?;
+    Ok(rows.iter().next().map(|row| row.get(0)))
+}
+
+/// Gets a setting, falling back to `default` when it is not stored. An invalid stored
+/// value is reported and replaced by `default` too, so it cannot keep the site from starting.
+fn get_or<T: Value>(conn: &GenericConnection, name: &str, default: T) -> Result<T> {
+    Ok(match get_raw(conn, name)? {
+        Some(value) => T::parse(&value).unwrap_or_else(|e| {
+            eprintln!("ignoring invalid setting {}: {}", name, e);
+            default
+        }),
+        None => default,
+    })
+}
+
+pub fn set<T: Value>(conn: &GenericConnection, name: &str, value: &T) -> Result<()> {
+    conn.execute(
+        "INSERT INTO setting (name, value, updated_at) VALUES ($1, $2, CURRENT_TIMESTAMP)
+                               ON CONFLICT (name)
+                               DO UPDATE SET value = EXCLUDED.value, updated_at = EXCLUDED.updated_at",
+        &[&name, &value.format()],
+    )?;
+    Ok(())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    pub site_name: String,
+    pub description: String,
+    /// Public URL of the site without a trailing slash, e.g. `https://example.com`.
+    pub base_url: String,
+    /// Time zone dates are shown in.
+    pub timezone: FixedOffset,
+    pub posts_per_page: i64,
+    pub footer: String,
+    /// Language of the public pages, and of the admin for browsers preferring
+    /// none of the supported ones.
+    pub locale: Locale,
+    /// When a setting was last changed. `None` while all are defaults.
+    pub updated_at: Option<DateTime<Local>>,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            site_name: "雑記".to_owned(),
+            description: String::new(),
+            base_url: String::new(),
+            timezone: FixedOffset::east(9 * 3600),
+            posts_per_page: 10,
+            footer: String::new(),
+            locale: Locale::Ja,
+            updated_at: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Loads the stored settings, taking the others, and any stored value that is
+    /// invalid, from `defaults`.
+    pub fn load(conn: &GenericConnection, defaults: Settings) -> Result<Settings> {
+        let rows = conn.query("SELECT max(updated_at) FROM setting", &[])?;
+        Ok(Settings {
+            site_name: get_or(conn, "site_name", defaults.site_name)?,
+            description: get_or(conn, "description", defaults.description)?,
+            base_url: get_or(conn, "base_url", defaults.base_url)?,
+            timezone: get_or(conn, "timezone", defaults.timezone)?,
+            // Pages are counted by dividing by this, so it is at least 1 whatever is stored.
+            posts_per_page: get_or(conn, "posts_per_page", defaults.posts_per_page)?.max(1),
+            footer: get_or(conn, "footer", defaults.footer)?,
+            locale: get_or(conn, "locale", defaults.locale)?,
+            updated_at: rows.get(0).get(0),
+        })
+    }
+
+    pub fn save(&self, conn: &GenericConnection) -> Result<()> {
+        set(conn, "site_name", &self.site_name)?;
+        set(conn, "description", &self.description)?;
+        set(conn, "base_url", &self.base_url)?;
+        set(conn, "timezone", &self.timezone)?;
+        set(conn, "posts_per_page", &self.posts_per_page)?;
+        set(conn, "footer", &self.footer)?;
+        set(conn, "locale", &self.locale)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use db::testutil;
+
+    #[test]
+    fn timezone() {
+        assert_eq!(FixedOffset::parse("+09:00").unwrap(), FixedOffset::east(9 * 3600));
+        assert_eq!(FixedOffset::parse("-05:30").unwrap(), FixedOffset::west(5 * 3600 + 1800));
+        assert_eq!(FixedOffset::parse("UTC").unwrap(), FixedOffset::east(0));
+        assert_eq!(FixedOffset::east(9 * 3600).format(), "+09:00");
+        assert!(FixedOffset::parse("Asia/Tokyo").is_err());
+        assert!(FixedOffset::parse("+09:75").is_err());
+    }
+
+    #[test]
+    fn get_and_set() {
+        testutil::with_db(|conn| {
+            conn.execute("DELETE FROM setting", &[]).unwrap();
+            assert_eq!(get_or(conn, "posts_per_page", 0i64).unwrap(), 0);
+            set(conn, "posts_per_page", &5i64).unwrap();
+            set(conn, "posts_per_page", &20i64).unwrap();
+            assert_eq!(get_or(conn, "posts_per_page", 0i64).unwrap(), 20);
+            set(conn, "posts_per_page", &"many".to_owned()).unwrap();
+            assert_eq!(get_or(conn, "posts_per_page", 0i64).unwrap(), 0);
+        });
+    }
+
+    #[test]
+    fn load_and_save() {
+        testutil::with_db(|conn| {
+            conn.execute("DELETE FROM setting", &[]).unwrap();
+            let defaults = Settings::default();
+            assert_eq!(Settings::load(conn, defaults.clone()).unwrap(), defaults);
+            let settings = Settings {
+                site_name: "blog".to_owned(),
+                footer: "© me".to_owned(),
+                locale: Locale::En,
+                ..Settings::default()
+            };
+            settings.save(conn).unwrap();
+            let loaded = Settings::load(conn, Settings::default()).unwrap();
+            assert_eq!(loaded.site_name, "blog");
+            assert_eq!(loaded.footer, "© me");
+            assert_eq!(loaded.locale, Locale::En);
+            assert!(loaded.updated_at.is_some());
+        });
+    }
+
+    #[test]
+    fn load_zero_posts_per_page() {
+        testutil::with_db(|conn| {
+            conn.execute("DELETE FROM setting", &[]).unwrap();
+            set(conn, "posts_per_page", &0i64).unwrap();
+            let loaded = Settings::load(conn, Settings::default()).unwrap();
+            assert_eq!(loaded.posts_per_page, 1);
+        });
+    }
+
+    #[test]
+    fn load_invalid_values() {
+        testutil::with_db(|conn| {
+            conn.execute("DELETE FROM setting", &[]).unwrap();
+            set(conn, "timezone", &"Asia/Tokyo".to_owned()).unwrap();
+            set(conn, "locale", &"xx".to_owned()).unwrap();
+            set(conn, "site_name", &"blog".to_owned()).unwrap();
+            let defaults = Settings::default();
+            let loaded = Settings::load(conn, defaults.clone()).unwrap();
+            assert_eq!(loaded.timezone, defaults.timezone);
+            assert_eq!(loaded.locale, defaults.locale);
+            assert_eq!(loaded.site_name, "blog");
+        });
+    }
+}
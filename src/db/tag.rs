@@ -0,0 +1,70 @@
+use errors::*;
+
+use postgres::GenericConnection;
+
+#[derive(Debug, PartialEq)]
+pub struct Tag {
+    pub id: i32,
+    pub name: String,
+}
+
+impl Tag {
+    /// Replaces the tags of an article, creating tags that do not exist yet.
+    pub fn set_for_article(conn: &GenericConnection, article_id: i32, names: &[String]) -> Result<()> {
+        conn.execute("DELETE FROM article_tag WHERE article = $1", &[&article_id])?;
+        for name in names {
+            conn.execute(
+                "INSERT INTO tag (name) VALUES ($1) ON CONFLICT (name) DO NOTHING",
+                &[name],
+            )?;
+            conn.execute(
+                "INSERT INTO article_tag (article, tag)
+                               SELECT $1, id FROM tag WHERE name = $2
+                               ON CONFLICT DO NOTHING",
+                &[&article_id, name],
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn list_for_article(conn: &GenericConnection, article_id: i32) -> Result<Vec<Tag>> {
+        let rows = conn.query(
+            "SELECT t.id, t.name FROM tag t JOIN article_tag at ON at.tag = t.id
+                               WHERE at.article = $1
+                               ORDER BY t.name",
+            &[&article_id],
+        )?;
+        let tags = rows.iter()
+            .map(|row| Tag {
+                id: row.get(0),
+                name: row.get(1),
+            })
+            .collect();
+        Ok(tags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use db::testutil;
+
+    #[test]
+    fn set_for_article() {
+        testutil::with_db(|conn| {
+            conn.execute(
+                "INSERT INTO article (id, title, body, author, created_at, updated_at)
+                          VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)",
+                &[&1, &"title", &"body", &1],
+            ).unwrap();
+            Tag::set_for_article(conn, 1, &["rust".to_owned(), "web".to_owned()]).unwrap();
+            Tag::set_for_article(conn, 1, &["web".to_owned()]).unwrap();
+            let names: Vec<String> = Tag::list_for_article(conn, 1)
+                .unwrap()
+                .into_iter()
+                .map(|tag| tag.name)
+                .collect();
+            assert_eq!(names, vec!["web".to_owned()]);
+        });
+    }
+}
@@ -1,5 +1,7 @@
 error_chain! {
     foreign_links {
+        Io(::std::io::Error);
+        Json(::serde_json::Error);
         Postgres(::postgres::Error);
         R2d2(::r2d2::Error);
         OpenSsl(::openssl::error::ErrorStack);
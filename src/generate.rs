@@ -0,0 +1,325 @@
+//! Renders the public site into a directory of static files.
+
+use db::article::Article;
+use db::series::Series;
+use errors::*;
+use views::{self, Site};
+use views::card::Cards;
+
+use chrono::{DateTime, Local};
+use maud::Markup;
+use postgres::GenericConnection;
+use xml::writer::{self, EmitterConfig, EventWriter, XmlEvent};
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::Path;
+
+/// Number of the latest articles in the feed.
+const FEED_ARTICLES: usize = 20;
+
+/// Writes every public page, the social preview images when `cards` is given and
+/// the static files of the site under `out`, along with an Atom feed and a sitemap
+/// when the site has a base URL. Returns the number of pages written.
+pub fn generate(
+    conn: &GenericConnection,
+    site: &Site,
+    cards: Option<&Cards>,
+    out: &Path,
+) -> Result<usize> {
+    fs::create_dir_all(out)?;
+    // The theme's directory comes first and is copied last to replace the others.
+    for dir in site.assets.dirs().iter().rev() {
+        if dir.is_dir() {
+            copy_dir(dir, out)?;
+        }
+    }
+    for (path, fingerprinted) in site.assets.files() {
+        if let Some(file) = site.assets.find(&path) {
+            fs::copy(file, out.join(fingerprinted))?;
+        }
+    }
+
+    let mut articles = Article::list_published(conn)?;
+    for article in &mut articles {
+        views::prepare(conn, article, site.extensions)?;
+    }
+    if !site.settings.base_url.is_empty() {
+        let mut latest: Vec<&Article> = articles.iter().collect();
+        latest.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.id.cmp(&a.id)));
+        latest.truncate(FEED_ARTICLES);
+        write_feed(File::create(out.join("feed.xml"))?, &latest, site)
+            .chain_err(|| "failed to write the feed")?;
+    }
+    // Site absolute paths of the pages, with when they last changed if known.
+    let mut paths: Vec<(String, Option<DateTime<Local>>)> = Vec::new();
+    let mut pages = 0;
+    for article in &articles {
+        let navigation = Article::navigation(conn, article, views::RELATED_ARTICLES)?;
+        write_page(
+            &out.join(format!("article/{}/index.html", article.id)),
+            views::render_article(article, &navigation, site)?,
+            2,
+        )?;
+        paths.push((format!("/article/{}", article.id), Some(article.updated_at)));
+        if let Some(cards) = cards {
+            let image = cards.get(article, site)?;
+            File::create(out.join(format!("article/{}/og.png", article.id)))?.write_all(&image)?;
+        }
+        pages += 1;
+    }
+    let featured = Article::list_featured(conn, views::FEATURED_ARTICLES)?;
+    let index_pages = views::page_count(articles.len() as i64, site);
+    let per_page = site.settings.posts_per_page as usize;
+    let mut articles = articles.into_iter();
+    for page in 1..index_pages + 1 {
+        let page_articles = articles.by_ref().take(per_page).collect();
+        let (path, depth) = if page == 1 {
+            paths.push(("/".to_owned(), None));
+            (out.join("index.html"), 0)
+        } else {
+            paths.push((format!("/page/{}", page), None));
+            (out.join(format!("page/{}/index.html", page)), 2)
+        };
+        let page_featured = if page == 1 { &featured[..] } else { &[] };
+        let markup = views::render_index(page_articles, page_featured, page, index_pages, site)?;
+        write_page(&path, markup, depth)?;
+        pages += 1;
+    }
+    for series in Series::list(conn)? {
+        let mut articles = Article::list_published_in_series(conn, series.id)?;
+        if articles.is_empty() {
+            continue;
+        }
+        for article in &mut articles {
+            views::prepare(conn, article, site.extensions)?;
+        }
+        write_page(
+            &out.join(format!("series/{}/index.html", series.slug)),
+            views::render_series(&series, &articles, site)?,
+            2,
+        )?;
+        paths.push((format!("/series/{}", series.slug), Some(series.updated_at)));
+        pages += 1;
+    }
+    write_page(&out.join("404.html"), views::render_not_found(site), 0)?;
+    if !site.settings.base_url.is_empty() {
+        write_sitemap(File::create(out.join("sitemap.xml"))?, &paths, site)
+            .chain_err(|| "failed to write the sitemap")?;
+    }
+    Ok(pages + 1)
+}
+
+/// Writes an Atom feed of `articles`, showing the same part of each as the index.
+fn write_feed<W: Write>(out: W, articles: &[&Article], site: &Site) -> writer::Result<()> {
+    let url = |path: &str| site.url(path).unwrap_or_default();
+    let date = |time: &DateTime<Local>| time.with_timezone(&site.settings.timezone).to_rfc3339();
+    let home = url("/");
+    let feed = url("/feed.xml");
+    let updated = articles
+        .iter()
+        .map(|article| article.updated_at)
+        .chain(site.settings.updated_at)
+        .max()
+        .unwrap_or_else(Local::now);
+
+    let mut writer = EmitterConfig::new().perform_indent(true).create_writer(out);
+    writer.write(XmlEvent::start_element("feed").default_ns("http://www.w3.org/2005/Atom"))?;
+    text_element(&mut writer, "title", &site.settings.site_name)?;
+    if !site.settings.description.is_empty() {
+        text_element(&mut writer, "subtitle", &site.settings.description)?;
+    }
+    writer.write(XmlEvent::start_element("link").attr("href", &home))?;
+    writer.write(XmlEvent::end_element())?;
+    writer.write(XmlEvent::start_element("link").attr("rel", "self").attr("href", &feed))?;
+    writer.write(XmlEvent::end_element())?;
+    text_element(&mut writer, "id", &home)?;
+    text_element(&mut writer, "updated", &date(&updated))?;
+    for article in articles {
+        let link = url(&format!("/article/{}", article.id));
+        writer.write(XmlEvent::start_element("entry"))?;
+        text_element(&mut writer, "title", &article.title)?;
+        writer.write(XmlEvent::start_element("link").attr("href", &link))?;
+        writer.write(XmlEvent::end_element())?;
+        text_element(&mut writer, "id", &link)?;
+        text_element(&mut writer, "published", &date(&article.created_at))?;
+        text_element(&mut writer, "updated", &date(&article.updated_at))?;
+        writer.write(XmlEvent::start_element("author"))?;
+        text_element(&mut writer, "name", &article.author.name)?;
+        writer.write(XmlEvent::end_element())?;
+        if let Some(ref rendered) = article.rendered {
+            let html = rendered.summary.as_ref().unwrap_or(&rendered.body);
+            writer.write(XmlEvent::start_element("summary").attr("type", "html"))?;
+            writer.write(XmlEvent::characters(html))?;
+            writer.write(XmlEvent::end_element())?;
+        }
+        writer.write(XmlEvent::end_element())?;
+    }
+    writer.write(XmlEvent::end_element())
+}
+
+/// Writes a sitemap listing the pages at `paths`.
+fn write_sitemap<W: Write>(
+    out: W,
+    paths: &[(String, Option<DateTime<Local>>)],
+    site: &Site,
+) -> writer::Result<()> {
+    let mut writer = EmitterConfig::new().perform_indent(true).create_writer(out);
+    writer.write(
+        XmlEvent::start_element("urlset").default_ns("http://www.sitemaps.org/schemas/sitemap/0.9"),
+    )?;
+    for &(ref path, ref last_modified) in paths {
+        writer.write(XmlEvent::start_element("url"))?;
+        text_element(&mut writer, "loc", &site.url(path).unwrap_or_default())?;
+        if let Some(ref time) = *last_modified {
+            let date = time.with_timezone(&site.settings.timezone).to_rfc3339();
+            text_element(&mut writer, "lastmod", &date)?;
+        }
+        writer.write(XmlEvent::end_element())?;
+    }
+    writer.write(XmlEvent::end_element())
+}
+
+fn text_element<W: Write>(writer: &mut EventWriter<W>, name: &str, text: &str) -> writer::Result<()> {
+    writer.write(XmlEvent::start_element(name))?;
+    writer.write(XmlEvent::characters(text))?;
+    writer.write(XmlEvent::end_element())
+}
+
+fn write_page(path: &Path, markup: Markup, depth: usize) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let html = relativize(&markup.into_string(), depth);
+    File::create(path)?.write_all(html.as_bytes())?;
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            fs::create_dir_all(&target)?;
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Rewrites site absolute `href` and `src` attributes of a page `depth` directories
+/// below the root into relative links, pointing directory-like paths at their
+/// `index.html` so the output also works when opened from the file system.
+fn relativize(html: &str, depth: usize) -> String {
+    let prefix = "../".repeat(depth);
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    loop {
+        let next = ["href=\"/", "src=\"/"]
+            .iter()
+            .filter_map(|attr| rest.find(attr).map(|i| (i, attr.len())))
+            .min();
+        let (start, len) = match next {
+            Some(next) => next,
+            None => break,
+        };
+        out.push_str(&rest[..start + len - 1]);
+        rest = &rest[start + len - 1..];
+        let end = rest.find('"').unwrap_or(rest.len());
+        out.push_str(&relative_url(&rest[..end], &prefix));
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn relative_url(url: &str, prefix: &str) -> String {
+    if url.starts_with("//") {
+        return url.to_owned();
+    }
+    let split = url.find(|c: char| c == '?' || c == '#').unwrap_or(url.len());
+    let (path, suffix) = url.split_at(split);
+    let path = &path[1..];
+    let file_name = path.rsplit('/').next().unwrap_or("");
+    if file_name.is_empty() {
+        format!("{}{}index.html{}", prefix, path, suffix)
+    } else if !file_name.contains('.') {
+        format!("{}{}/index.html{}", prefix, path, suffix)
+    } else {
+        format!("{}{}{}", prefix, path, suffix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use db::article::Rendered;
+    use db::person::Person;
+
+    use chrono::TimeZone;
+
+    #[test]
+    fn relativize_links() {
+        let html = r#"<link href="/css/style.css"><a href="/">top</a><a href="/article/1#a">a</a><img src="//example.com/a.png">"#;
+        assert_eq!(
+            relativize(html, 2),
+            r#"<link href="../../css/style.css"><a href="../../index.html">top</a><a href="../../article/1/index.html#a">a</a><img src="//example.com/a.png">"#
+        );
+        assert_eq!(
+            relativize(r#"<a href="https://example.com/">x</a>"#, 0),
+            r#"<a href="https://example.com/">x</a>"#
+        );
+    }
+
+    #[test]
+    fn feed_and_sitemap() {
+        let mut site = Site::default();
+        site.settings.site_name = "雑記 & メモ".to_owned();
+        site.settings.base_url = "https://example.com".to_owned();
+        let time = Local.ymd(2018, 3, 1).and_hms(12, 0, 0);
+        let article = Article {
+            id: 1,
+            title: "<Rust>".to_owned(),
+            body: String::new(),
+            summary: None,
+            slug: None,
+            draft: false,
+            pinned: false,
+            featured: false,
+            created_at: time,
+            updated_at: time,
+            author: Person {
+                id: 1,
+                name: "admin".to_owned(),
+            },
+            rendered: Some(Rendered {
+                body: "<p>body</p>".to_owned(),
+                summary: None,
+            }),
+        };
+        let date = time.with_timezone(&site.settings.timezone).to_rfc3339();
+
+        let mut feed = Vec::new();
+        write_feed(&mut feed, &[&article], &site).unwrap();
+        let feed = String::from_utf8(feed).unwrap();
+        assert!(feed.contains(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#));
+        assert!(feed.contains("<title>雑記 &amp; メモ</title>"));
+        assert!(feed.contains(r#"<link rel="self" href="https://example.com/feed.xml" />"#));
+        assert!(feed.contains(&format!("<updated>{}</updated>", date)));
+        assert!(feed.contains("<title>&lt;Rust></title>"));
+        assert!(feed.contains("<id>https://example.com/article/1</id>"));
+        assert!(feed.contains(r#"<summary type="html">&lt;p>body&lt;/p></summary>"#));
+
+        let mut sitemap = Vec::new();
+        let paths = vec![("/".to_owned(), None), ("/article/1".to_owned(), Some(time))];
+        write_sitemap(&mut sitemap, &paths, &site).unwrap();
+        let sitemap = String::from_utf8(sitemap).unwrap();
+        assert!(sitemap.contains("<loc>https://example.com/</loc>"));
+        assert!(sitemap.contains(&format!(
+            "<loc>https://example.com/article/1</loc>\n    <lastmod>{}</lastmod>",
+            date
+        )));
+    }
+}
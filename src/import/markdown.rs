@@ -0,0 +1,266 @@
+//! Imports posts from a Jekyll or Hugo style directory of Markdown files with YAML
+//! (`---`) or TOML (`+++`) front matter.
+
+use super::{parse_date, Report};
+use db::article::{Article, ImportedArticle};
+use db::person::Person;
+use errors::*;
+
+use postgres::GenericConnection;
+use serde_yaml;
+use toml;
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct FrontMatter {
+    title: Option<String>,
+    summary: Option<String>,
+    description: Option<String>,
+    excerpt: Option<String>,
+    date: Option<String>,
+    lastmod: Option<String>,
+    last_modified_at: Option<String>,
+    tags: Tags,
+    categories: Tags,
+    slug: Option<String>,
+    draft: Option<bool>,
+    published: Option<bool>,
+}
+
+/// Tags are either a list or, as Jekyll allows, a space separated string.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Tags {
+    List(Vec<String>),
+    Words(String),
+}
+
+impl Default for Tags {
+    fn default() -> Tags {
+        Tags::List(Vec::new())
+    }
+}
+
+impl Tags {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            Tags::List(tags) => tags,
+            Tags::Words(words) => words.split_whitespace().map(|s| s.to_owned()).collect(),
+        }
+    }
+}
+
+pub fn import_dir(conn: &GenericConnection, dir: &Path, author: &Person) -> Result<Report> {
+    let mut files = Vec::new();
+    collect_files(dir, &mut files)?;
+    files.sort();
+
+    let mut report = Report::default();
+    for path in files {
+        let name = path.display().to_string();
+        let article = match read_file(&path) {
+            Ok(article) => article,
+            Err(e) => {
+                report.invalid.push((name, e.to_string()));
+                continue;
+            }
+        };
+        match Article::import(conn, &article, author)? {
+            Some(_) => report.created.push(name),
+            None => report.skipped.push((name, "already imported".to_owned())),
+        }
+    }
+    Ok(report)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if path.extension()
+            .map(|ext| ext == "md" || ext == "markdown")
+            .unwrap_or(false)
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn read_file(path: &Path) -> Result<ImportedArticle> {
+    let mut content = String::new();
+    File::open(path)?.read_to_string(&mut content)?;
+    parse(path, &content)
+}
+
+fn parse(path: &Path, content: &str) -> Result<ImportedArticle> {
+    let (front_matter, body) = split_front_matter(content)?;
+
+    // Jekyll posts are named like `2017-12-01-slug.md`, Hugo bundles like `slug/index.md`.
+    let stem = path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default();
+    let stem = if stem == "index" {
+        path.parent()
+            .and_then(|dir| dir.file_name())
+            .and_then(|name| name.to_str())
+            .unwrap_or(stem)
+    } else {
+        stem
+    };
+    let (file_date, file_slug) = match (stem.get(..10), stem.get(11..)) {
+        (Some(date), Some(slug)) if !slug.is_empty() && parse_date(date).is_some() => {
+            (Some(date), slug)
+        }
+        _ => (None, stem),
+    };
+
+    let title = front_matter.title.chain_err(|| "title is missing")?;
+    let date = front_matter
+        .date
+        .as_ref()
+        .map(|date| date.as_str())
+        .or(file_date)
+        .chain_err(|| "date is missing")?;
+    let created_at = parse_date(date).chain_err(|| format!("invalid date: {}", date))?;
+    let updated_at = match front_matter.lastmod.or(front_matter.last_modified_at) {
+        Some(date) => parse_date(&date).chain_err(|| format!("invalid date: {}", date))?,
+        None => created_at,
+    };
+    let slug = front_matter.slug.unwrap_or_else(|| file_slug.to_owned());
+    if slug.is_empty() {
+        bail!("slug is missing");
+    }
+
+    let mut tags = front_matter.tags.into_vec();
+    for category in front_matter.categories.into_vec() {
+        if !tags.contains(&category) {
+            tags.push(category);
+        }
+    }
+
+    Ok(ImportedArticle {
+        title: title,
+        body: body.trim().replace("<!--more-->", "<!-- more -->"),
+        summary: front_matter
+            .summary
+            .or(front_matter.excerpt)
+            .or(front_matter.description),
+        slug: slug,
+        draft: front_matter.draft.unwrap_or(false) || !front_matter.published.unwrap_or(true),
+        tags: tags,
+        created_at: created_at,
+        updated_at: updated_at,
+    })
+}
+
+fn split_front_matter(content: &str) -> Result<(FrontMatter, &str)> {
+    let content = content.trim_left_matches('\u{feff}');
+    let delimiter = match content.lines().next().map(|line| line.trim_right()) {
+        Some("---") => "---",
+        Some("+++") => "+++",
+        _ => bail!("front matter is missing"),
+    };
+    let start = content.find('\n').map(|i| i + 1).unwrap_or(content.len());
+    // The front matter ends at a line of just the delimiter, not at one merely
+    // starting with it such as `----` in a block scalar.
+    let mut offset = start;
+    let mut end = None;
+    for line in content[start..].split('\n') {
+        if line.trim_right() == delimiter {
+            end = Some(offset);
+            break;
+        }
+        offset += line.len() + 1;
+    }
+    let end = end.chain_err(|| "front matter is not closed")?;
+    let source = &content[start..end];
+    let body = &content[end + delimiter.len()..];
+
+    let front_matter = if delimiter == "---" {
+        serde_yaml::from_str(source).chain_err(|| "invalid YAML front matter")?
+    } else {
+        source
+            .parse::<toml::Value>()
+            .and_then(|value| value.try_into())
+            .chain_err(|| "invalid TOML front matter")?
+    };
+    Ok((front_matter, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_yaml() {
+        let content = "---\n\
+                       title: Hello\n\
+                       date: 2017-12-01 12:34:56 +0900\n\
+                       tags: [rust, web]\n\
+                       categories: diary\n\
+                       ---\n\
+                       \n\
+                       intro\n\
+                       <!--more-->\n\
+                       rest\n";
+        let article = parse(Path::new("_posts/2017-12-01-hello.md"), content).unwrap();
+        assert_eq!(article.title, "Hello");
+        assert_eq!(article.slug, "hello");
+        assert_eq!(article.body, "intro\n<!-- more -->\nrest");
+        assert_eq!(article.tags, vec!["rust", "web", "diary"]);
+        assert!(!article.draft);
+        assert_eq!(article.created_at, parse_date("2017-12-01T12:34:56+09:00").unwrap());
+    }
+
+    #[test]
+    fn parse_toml() {
+        let content = "+++\n\
+                       title = \"Hello\"\n\
+                       date = 2017-12-01T12:34:56+09:00\n\
+                       slug = \"custom\"\n\
+                       draft = true\n\
+                       +++\n\
+                       body\n";
+        let article = parse(Path::new("content/post/hello/index.md"), content).unwrap();
+        assert_eq!(article.slug, "custom");
+        assert_eq!(article.body, "body");
+        assert!(article.draft);
+        assert_eq!(article.created_at, parse_date("2017-12-01T12:34:56+09:00").unwrap());
+    }
+
+    #[test]
+    fn parse_non_ascii_file_name() {
+        let content = "---\ntitle: 日本語\ndate: 2017-12-01\n---\nbody\n";
+        let article = parse(Path::new("_posts/日本語のタイトル.md"), content).unwrap();
+        assert_eq!(article.slug, "日本語のタイトル");
+        let article = parse(Path::new("_posts/2017-12-01-日本語.md"), content).unwrap();
+        assert_eq!(article.slug, "日本語");
+    }
+
+    #[test]
+    fn parse_delimiter_in_string() {
+        let content = "+++\n\
+                       title = \"Rules\"\n\
+                       summary = \"\"\"\n\
+                       ++++\n\
+                       +++x\"\"\"\n\
+                       +++\n\
+                       body\n";
+        let (front_matter, body) = split_front_matter(content).unwrap();
+        assert_eq!(front_matter.summary.as_ref().map(|s| &s[..]), Some("++++\n+++x"));
+        assert_eq!(body, "\nbody\n");
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(parse(Path::new("a.md"), "no front matter").is_err());
+        assert!(parse(Path::new("a.md"), "---\ndate: 2017-12-01\n---\n").is_err());
+        assert!(parse(Path::new("a.md"), "---\ntitle: a\n---\n").is_err());
+    }
+}
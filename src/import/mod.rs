@@ -0,0 +1,50 @@
+pub mod markdown;
+pub mod wordpress;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+
+/// What happened to each source (a file path or URL) during an import run.
+#[derive(Debug, Default, PartialEq)]
+pub struct Report {
+    pub created: Vec<String>,
+    pub skipped: Vec<(String, String)>,
+    pub invalid: Vec<(String, String)>,
+}
+
+/// Parses the date formats written by common blog engines. Dates without an offset
+/// are taken as local time.
+pub fn parse_date(s: &str) -> Option<DateTime<Local>> {
+    let s = s.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(s) {
+        return Some(date.with_timezone(&Local));
+    }
+    for format in &["%Y-%m-%d %H:%M:%S %z", "%Y-%m-%d %H:%M %z"] {
+        if let Ok(date) = DateTime::parse_from_str(s, format) {
+            return Some(date.with_timezone(&Local));
+        }
+    }
+    for format in &["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(s, format) {
+            return Local.from_local_datetime(&date).earliest();
+        }
+    }
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| Local.from_local_datetime(&date.and_hms(0, 0, 0)).earliest())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_date_test() {
+        let expected = "2017-12-01T12:34:56+09:00"
+            .parse::<DateTime<Local>>()
+            .unwrap();
+        assert_eq!(parse_date("2017-12-01T12:34:56+09:00"), Some(expected));
+        assert_eq!(parse_date("2017-12-01 12:34:56 +0900"), Some(expected));
+        assert!(parse_date("2017-12-01").is_some());
+        assert_eq!(parse_date("yesterday"), None);
+    }
+}
@@ -0,0 +1,293 @@
+//! Imports posts from a WordPress export (WXR) file.
+
+use super::{parse_date, Report};
+use db::article::{Article, ImportedArticle};
+use db::person::Person;
+use errors::*;
+
+use chrono::{DateTime, Local, TimeZone, Utc};
+use postgres::GenericConnection;
+use xml::reader::{EventReader, XmlEvent};
+
+use std::io::Read;
+
+/// An old post URL and the path of the article it was imported as.
+pub type Redirect = (String, String);
+
+#[derive(Debug, Default, PartialEq)]
+struct Item {
+    id: String,
+    title: String,
+    link: String,
+    creator: String,
+    content: String,
+    excerpt: String,
+    post_name: String,
+    post_type: String,
+    status: String,
+    post_date: String,
+    post_date_gmt: String,
+    post_modified_gmt: String,
+    tags: Vec<String>,
+}
+
+pub fn import<R: Read>(
+    conn: &GenericConnection,
+    source: R,
+) -> Result<(Report, Vec<Redirect>)> {
+    let mut report = Report::default();
+    let mut redirects = Vec::new();
+    for item in parse(source)? {
+        let name = if item.link.is_empty() {
+            format!("post {}", item.id)
+        } else {
+            item.link.clone()
+        };
+        if item.post_type != "post" {
+            report.skipped.push((name, format!("post type is {}", item.post_type)));
+            continue;
+        }
+        if item.status == "trash" || item.status == "auto-draft" {
+            report.skipped.push((name, format!("status is {}", item.status)));
+            continue;
+        }
+        let article = match convert(&item) {
+            Ok(article) => article,
+            Err(e) => {
+                report.invalid.push((name, e.to_string()));
+                continue;
+            }
+        };
+        let author = Person::get_or_create_locked(conn, &item.creator)?;
+        let id = match Article::import(conn, &article, &author)? {
+            Some(id) => {
+                report.created.push(name);
+                id
+            }
+            None => {
+                report.skipped.push((name, "already imported".to_owned()));
+                Article::find_id_by_slug(conn, &article.slug)?
+                    .chain_err(|| "failed to find imported article")?
+            }
+        };
+        if !item.link.is_empty() {
+            redirects.push((item.link, format!("/article/{}", id)));
+        }
+    }
+    Ok((report, redirects))
+}
+
+fn parse<R: Read>(source: R) -> Result<Vec<Item>> {
+    let mut items = Vec::new();
+    let mut item: Option<Item> = None;
+    let mut category_domain = None;
+    let mut text = String::new();
+    for event in EventReader::new(source) {
+        match event.chain_err(|| "invalid WXR file")? {
+            XmlEvent::StartElement { name, attributes, .. } => {
+                text.clear();
+                if name.local_name == "item" && name.prefix.is_none() {
+                    item = Some(Item::default());
+                } else if name.local_name == "category" && name.prefix.is_none() {
+                    category_domain = attributes
+                        .into_iter()
+                        .find(|attr| attr.name.local_name == "domain")
+                        .map(|attr| attr.value);
+                }
+            }
+            XmlEvent::Characters(s) | XmlEvent::CData(s) => text.push_str(&s),
+            XmlEvent::EndElement { name } => {
+                let value = text.trim().to_owned();
+                text.clear();
+                if name.local_name == "item" && name.prefix.is_none() {
+                    items.extend(item.take());
+                    continue;
+                }
+                let item = match item.as_mut() {
+                    Some(item) => item,
+                    None => continue,
+                };
+                match (name.prefix.as_ref().map(|p| p.as_str()), name.local_name.as_str()) {
+                    (None, "title") => item.title = value,
+                    (None, "link") => item.link = value,
+                    (None, "category") => {
+                        let domain = category_domain.take();
+                        let is_tag = domain
+                            .as_ref()
+                            .map(|d| d == "category" || d == "post_tag")
+                            .unwrap_or(false);
+                        if is_tag && value != "Uncategorized" && !item.tags.contains(&value) {
+                            item.tags.push(value);
+                        }
+                    }
+                    (Some("dc"), "creator") => item.creator = value,
+                    (Some("content"), "encoded") => item.content = value,
+                    (Some("excerpt"), "encoded") => item.excerpt = value,
+                    (Some("wp"), "post_id") => item.id = value,
+                    (Some("wp"), "post_name") => item.post_name = value,
+                    (Some("wp"), "post_type") => item.post_type = value,
+                    (Some("wp"), "status") => item.status = value,
+                    (Some("wp"), "post_date") => item.post_date = value,
+                    (Some("wp"), "post_date_gmt") => item.post_date_gmt = value,
+                    (Some("wp"), "post_modified_gmt") => item.post_modified_gmt = value,
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(items)
+}
+
+fn convert(item: &Item) -> Result<ImportedArticle> {
+    if item.title.is_empty() {
+        bail!("title is missing");
+    }
+    if item.creator.is_empty() {
+        bail!("author is missing");
+    }
+    // Unpublished posts carry a zero GMT date; fall back to the site local date.
+    let created_at = parse_gmt(&item.post_date_gmt)
+        .or_else(|| parse_date(&item.post_date))
+        .chain_err(|| format!("invalid date: {}", item.post_date))?;
+    let updated_at = parse_gmt(&item.post_modified_gmt).unwrap_or(created_at);
+    let slug = if item.post_name.is_empty() {
+        format!("wp-{}", item.id)
+    } else {
+        item.post_name.clone()
+    };
+    Ok(ImportedArticle {
+        title: item.title.clone(),
+        body: autop(&item.content),
+        summary: if item.excerpt.is_empty() {
+            None
+        } else {
+            Some(item.excerpt.clone())
+        },
+        slug: slug,
+        draft: item.status != "publish",
+        tags: item.tags.clone(),
+        created_at: created_at,
+        updated_at: updated_at,
+    })
+}
+
+fn parse_gmt(s: &str) -> Option<DateTime<Local>> {
+    Utc.datetime_from_str(s, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .map(|date| date.with_timezone(&Local))
+}
+
+const BLOCK_TAGS: &'static [&'static str] = &[
+    "<p", "<div", "<h1", "<h2", "<h3", "<h4", "<h5", "<h6", "<ul", "<ol", "<dl",
+    "<blockquote", "<pre", "<table", "<figure", "<hr", "<!--",
+];
+
+/// Turns WordPress post content, where blank lines separate paragraphs and single
+/// newlines are line breaks, into HTML blocks that Markdown passes through unchanged.
+fn autop(content: &str) -> String {
+    let content = content
+        .replace("\r\n", "\n")
+        .replace("<!--more-->", "<!-- more -->");
+    let mut blocks: Vec<String> = Vec::new();
+    let mut in_pre = false;
+    for chunk in content.split("\n\n") {
+        let chunk = chunk.trim_matches('\n');
+        if in_pre {
+            // Blank lines inside <pre> belong to the same block.
+            if let Some(last) = blocks.last_mut() {
+                last.push_str("\n\n");
+                last.push_str(chunk);
+            }
+        } else if chunk.trim().is_empty() {
+            continue;
+        } else if BLOCK_TAGS.iter().any(|tag| chunk.trim_left().starts_with(tag)) {
+            blocks.push(chunk.to_owned());
+        } else {
+            blocks.push(format!("<p>{}</p>", chunk.trim().replace("\n", "<br />\n")));
+        }
+        if let Some(last) = blocks.last() {
+            in_pre = last.matches("<pre").count() > last.matches("</pre>").count();
+        }
+    }
+    blocks.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use db::testutil;
+
+    const WXR: &'static str = r#"<?xml version="1.0" encoding="UTF-8" ?>
+<rss version="2.0"
+    xmlns:content="http://purl.org/rss/1.0/modules/content/"
+    xmlns:dc="http://purl.org/dc/elements/1.1/"
+    xmlns:wp="http://wordpress.org/export/1.2/">
+<channel>
+    <title>Old blog</title>
+    <item>
+        <title>Hello</title>
+        <link>http://example.com/2017/12/01/hello/</link>
+        <dc:creator><![CDATA[alice]]></dc:creator>
+        <content:encoded><![CDATA[First line
+second line
+
+<!--more-->
+
+<pre>code
+
+more code</pre>]]></content:encoded>
+        <wp:post_id>42</wp:post_id>
+        <wp:post_date><![CDATA[2017-12-01 12:34:56]]></wp:post_date>
+        <wp:post_date_gmt><![CDATA[2017-12-01 03:34:56]]></wp:post_date_gmt>
+        <wp:post_modified_gmt><![CDATA[2017-12-02 03:34:56]]></wp:post_modified_gmt>
+        <wp:post_name><![CDATA[hello]]></wp:post_name>
+        <wp:status><![CDATA[publish]]></wp:status>
+        <wp:post_type><![CDATA[post]]></wp:post_type>
+        <category domain="category" nicename="diary"><![CDATA[Diary]]></category>
+        <category domain="post_tag" nicename="rust"><![CDATA[Rust]]></category>
+    </item>
+    <item>
+        <title>photo.jpg</title>
+        <wp:post_type><![CDATA[attachment]]></wp:post_type>
+    </item>
+</channel>
+</rss>"#;
+
+    #[test]
+    fn parse_items() {
+        let items = parse(WXR.as_bytes()).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].creator, "alice");
+        assert_eq!(items[0].tags, vec!["Diary", "Rust"]);
+        assert_eq!(items[1].post_type, "attachment");
+    }
+
+    #[test]
+    fn convert_item() {
+        let items = parse(WXR.as_bytes()).unwrap();
+        let article = convert(&items[0]).unwrap();
+        assert_eq!(article.slug, "hello");
+        assert!(!article.draft);
+        assert_eq!(
+            article.body,
+            "<p>First line<br />\nsecond line</p>\n\n<!-- more -->\n\n<pre>code\n\nmore code</pre>"
+        );
+        assert_eq!(
+            article.created_at,
+            "2017-12-01T12:34:56+09:00".parse::<DateTime<Local>>().unwrap()
+        );
+    }
+
+    #[test]
+    fn import_redirects() {
+        testutil::with_db(|conn| {
+            let (report, redirects) = import(conn, WXR.as_bytes()).unwrap();
+            assert_eq!(report.created.len(), 1);
+            assert_eq!(report.skipped.len(), 1);
+            let (_, again) = import(conn, WXR.as_bytes()).unwrap();
+            assert_eq!(redirects, again);
+            assert_eq!(redirects[0].0, "http://example.com/2017/12/01/hello/");
+        });
+    }
+}
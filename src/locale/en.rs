@@ -0,0 +1,135 @@
+use super::Messages;
+
+pub static MESSAGES: Messages = Messages {
+    language_name: "English",
+    date_format: "%B %-d, %Y",
+    datetime_format: "%F %T",
+    series_part_format: "Part {part} of {parts}",
+
+    posted_on: "Posted on ",
+    read_more: "Read more",
+    pinned: "Pinned",
+    featured_articles: "Featured articles",
+    previous_article: "Previous article",
+    next_article: "Next article",
+    related_articles: "Related articles",
+    series: "Series",
+    previous_page: "Previous page",
+    next_page: "Next page",
+    not_found: "Page not found.",
+    internal_error: "An internal error occurred.",
+
+    admin: "Admin",
+    login: "Log in",
+    logout: "Log out",
+    username: "Username:",
+    password: "Password:",
+    login_succeeded: "Logged in.",
+    login_failed: "Invalid username or password.",
+    logged_out: "Logged out.",
+    back_to_top: "Back to top",
+
+    article_list: "Articles",
+    title: "Title",
+    created_at: "Created",
+    updated_at: "Updated",
+    draft: "(draft)",
+    edit: "Edit",
+    delete: "Delete",
+    create: "Create",
+    new_article: "Write an article",
+    show_article: "Article",
+    create_article: "New article",
+    edit_article: "Edit article",
+    delete_article: "Delete article",
+    title_label: "Title:",
+    body_label: "Body:",
+    summary_label: "Summary:",
+    title_required: "Please enter a title.",
+    title_too_long: "The title must be at most {max} characters.",
+    body_too_large: "The body must be at most {max} KiB.",
+    summary_too_long: "The summary must be at most {max} characters.",
+    fix_errors: "The article was not saved. Please fix the fields below.",
+    article_created: "The article was created.",
+    article_updated: "The article was updated.",
+    article_deleted: "The article was deleted.",
+    confirm_delete: "Are you sure you want to delete this article?",
+    featured: "Featured",
+    pin: "Pin",
+    unpin: "Unpin",
+    feature: "Feature",
+    unfeature: "Unfeature",
+    autosave_found: "There are unsaved changes, saved automatically on ",
+    restore: "Restore",
+    discard: "Discard",
+    autosaved: "Saved automatically at ",
+    autosave_failed: "Could not save automatically.",
+    autosave_restored: "The unsaved changes were restored.",
+    autosave_discarded: "The unsaved changes were discarded.",
+    edit_conflict: "Edit conflict",
+    conflict_found: "Someone else saved this article after you started editing it, on ",
+    conflict_help: "Your changes have not been saved. Merge them with the current version and save again.",
+    differences: "Differences from the current version",
+    current_version: "Current version",
+    your_version: "Your version",
+
+    site_settings: "Site settings",
+    site_name_label: "Site name:",
+    description_label: "Description:",
+    base_url_label: "Base URL:",
+    timezone_label: "Time zone:",
+    posts_per_page_label: "Articles per page:",
+    footer_label: "Footer:",
+    locale_label: "Language:",
+    save: "Save",
+    site_name_required: "Please enter a site name.",
+    invalid_base_url: "The base URL must start with http:// or https://.",
+    invalid_timezone: "Enter the time zone as an offset such as +09:00.",
+    invalid_posts_per_page: "Articles per page must be at least 1.",
+    invalid_locale: "The language is not supported.",
+    settings_saved: "The settings were saved.",
+
+    series_list: "Series",
+    new_series: "Start a series",
+    create_series: "New series",
+    edit_series: "Edit series",
+    delete_series: "Delete series",
+    slug_label: "Slug:",
+    series_parts: "Articles in the series",
+    article_label: "Article:",
+    add_part: "Add",
+    move_up: "Up",
+    move_down: "Down",
+    remove_part: "Remove",
+    no_parts: "The series has no articles yet.",
+    invalid_slug: "The slug may only contain lowercase letters, digits and hyphens.",
+    slug_taken: "The slug is used by another series.",
+    series_created: "The series was created.",
+    series_updated: "The series was updated.",
+    series_deleted: "The series was deleted.",
+    parts_updated: "The articles in the series were updated.",
+    confirm_delete_series: "Are you sure you want to delete this series? Its articles are kept.",
+    audit_log: "Audit log",
+    action_label: "Action:",
+    actor_label: "By:",
+    all_actions: "All actions",
+    all_actors: "Anyone",
+    filter: "Filter",
+    no_audit_entries: "No actions have been recorded.",
+    date: "Date",
+    actor: "By",
+    action: "Action",
+    target: "Target",
+    ip_address: "IP address",
+    audit_login: "Logged in",
+    audit_login_failed: "Failed to log in",
+    audit_logout: "Logged out",
+    audit_create_article: "Created an article",
+    audit_update_article: "Updated an article",
+    audit_delete_article: "Deleted an article",
+    audit_promote_article: "Pinned or featured an article",
+    audit_update_settings: "Updated the site settings",
+    audit_create_series: "Created a series",
+    audit_update_series: "Updated a series",
+    audit_delete_series: "Deleted a series",
+};
@@ -0,0 +1,135 @@
+use super::Messages;
+
+pub static MESSAGES: Messages = Messages {
+    language_name: "日本語",
+    date_format: "%Y年%-m月%-d日",
+    datetime_format: "%F %T",
+    series_part_format: "全{parts}回中の第{part}回",
+
+    posted_on: "Posted on ",
+    read_more: "続きを読む",
+    pinned: "固定",
+    featured_articles: "おすすめ記事",
+    previous_article: "前の記事",
+    next_article: "次の記事",
+    related_articles: "関連記事",
+    series: "連載",
+    previous_page: "前のページ",
+    next_page: "次のページ",
+    not_found: "ページが見つかりません。",
+    internal_error: "システムエラーが発生しました。",
+
+    admin: "管理",
+    login: "ログイン",
+    logout: "ログアウト",
+    username: "ユーザー名:",
+    password: "パスワード:",
+    login_succeeded: "ログインに成功しました。",
+    login_failed: "無効なユーザー名またはパスワードが指定されました。",
+    logged_out: "ログアウトしました。",
+    back_to_top: "トップに戻る",
+
+    article_list: "記事の一覧",
+    title: "タイトル",
+    created_at: "登録日時",
+    updated_at: "更新日時",
+    draft: "(下書き)",
+    edit: "編集",
+    delete: "削除",
+    create: "作成",
+    new_article: "記事を作成する",
+    show_article: "記事の表示",
+    create_article: "記事の作成",
+    edit_article: "記事の編集",
+    delete_article: "記事の削除",
+    title_label: "タイトル:",
+    body_label: "本文:",
+    summary_label: "概要:",
+    title_required: "タイトルを入力してください。",
+    title_too_long: "タイトルは{max}文字以内にしてください。",
+    body_too_large: "本文は{max}KiB以内にしてください。",
+    summary_too_long: "概要は{max}文字以内にしてください。",
+    fix_errors: "記事は保存されていません。以下の項目を修正してください。",
+    article_created: "記事が作成されました。",
+    article_updated: "記事が編集されました。",
+    article_deleted: "記事が削除されました。",
+    confirm_delete: "記事を削除します。よろしいですか？",
+    featured: "おすすめ",
+    pin: "固定する",
+    unpin: "固定を外す",
+    feature: "おすすめにする",
+    unfeature: "おすすめから外す",
+    autosave_found: "保存されていない変更があります。自動保存日時: ",
+    restore: "復元",
+    discard: "破棄",
+    autosaved: "自動保存しました: ",
+    autosave_failed: "自動保存に失敗しました。",
+    autosave_restored: "保存されていない変更を復元しました。",
+    autosave_discarded: "保存されていない変更を破棄しました。",
+    edit_conflict: "編集の競合",
+    conflict_found: "編集中に他の人がこの記事を保存しました。保存日時: ",
+    conflict_help: "あなたの変更はまだ保存されていません。現在の内容と統合してから保存し直してください。",
+    differences: "現在の内容との差分",
+    current_version: "現在の内容",
+    your_version: "あなたの編集内容",
+
+    site_settings: "サイトの設定",
+    site_name_label: "サイト名:",
+    description_label: "説明:",
+    base_url_label: "ベースURL:",
+    timezone_label: "タイムゾーン:",
+    posts_per_page_label: "1ページの記事数:",
+    footer_label: "フッター:",
+    locale_label: "言語:",
+    save: "保存",
+    site_name_required: "サイト名を入力してください。",
+    invalid_base_url: "ベースURLは http:// または https:// で始めてください。",
+    invalid_timezone: "タイムゾーンは +09:00 のような形式で入力してください。",
+    invalid_posts_per_page: "1ページの記事数には1以上の数を入力してください。",
+    invalid_locale: "対応していない言語です。",
+    settings_saved: "設定が保存されました。",
+
+    series_list: "連載の一覧",
+    new_series: "連載を作成する",
+    create_series: "連載の作成",
+    edit_series: "連載の編集",
+    delete_series: "連載の削除",
+    slug_label: "スラッグ:",
+    series_parts: "連載の記事",
+    article_label: "記事:",
+    add_part: "追加",
+    move_up: "上へ",
+    move_down: "下へ",
+    remove_part: "外す",
+    no_parts: "連載にはまだ記事がありません。",
+    invalid_slug: "スラッグには英小文字、数字とハイフンだけを使ってください。",
+    slug_taken: "スラッグは他の連載で使われています。",
+    series_created: "連載が作成されました。",
+    series_updated: "連載が編集されました。",
+    series_deleted: "連載が削除されました。",
+    parts_updated: "連載の記事が更新されました。",
+    confirm_delete_series: "連載を削除します。記事は削除されません。よろしいですか？",
+    audit_log: "操作履歴",
+    action_label: "操作:",
+    actor_label: "実行者:",
+    all_actions: "すべての操作",
+    all_actors: "すべての人",
+    filter: "絞り込む",
+    no_audit_entries: "記録された操作はありません。",
+    date: "日時",
+    actor: "実行者",
+    action: "操作",
+    target: "対象",
+    ip_address: "IPアドレス",
+    audit_login: "ログイン",
+    audit_login_failed: "ログイン失敗",
+    audit_logout: "ログアウト",
+    audit_create_article: "記事の作成",
+    audit_update_article: "記事の編集",
+    audit_delete_article: "記事の削除",
+    audit_promote_article: "記事の固定・おすすめ",
+    audit_update_settings: "サイト設定の変更",
+    audit_create_series: "連載の作成",
+    audit_update_series: "連載の編集",
+    audit_delete_series: "連載の削除",
+};
@@ -0,0 +1,241 @@
+//! Catalogs of the text shown in the UI, one per supported language.
+
+mod en;
+mod ja;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Locale {
+    Ja,
+    En,
+}
+
+impl Default for Locale {
+    fn default() -> Locale {
+        Locale::Ja
+    }
+}
+
+impl Locale {
+    pub const ALL: &'static [Locale] = &[Locale::Ja, Locale::En];
+
+    /// The language tag, e.g. `ja`.
+    pub fn code(&self) -> &'static str {
+        match *self {
+            Locale::Ja => "ja",
+            Locale::En => "en",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Locale> {
+        // Regional variants such as `en-US` share a catalog.
+        let language = code.trim().split('-').next().unwrap_or("").to_lowercase();
+        Locale::ALL
+            .iter()
+            .find(|locale| locale.code() == language)
+            .cloned()
+    }
+
+    pub fn messages(&self) -> &'static Messages {
+        match *self {
+            Locale::Ja => &ja::MESSAGES,
+            Locale::En => &en::MESSAGES,
+        }
+    }
+
+    /// Picks the supported locale the client prefers most from an
+    /// `Accept-Language` header.
+    pub fn negotiate(accept_language: &str) -> Option<Locale> {
+        let mut best: Option<(Locale, f32)> = None;
+        for item in accept_language.split(',') {
+            let mut parts = item.split(';').map(|part| part.trim());
+            let locale = match parts.next().and_then(Locale::from_code) {
+                Some(locale) => locale,
+                None => continue,
+            };
+            let q = parts
+                .filter_map(|param| {
+                    if param.starts_with("q=") {
+                        param[2..].parse::<f32>().ok()
+                    } else {
+                        None
+                    }
+                })
+                .next()
+                .unwrap_or(1.0);
+            // Earlier entries win ties.
+            if q > 0.0 && best.map(|(_, best_q)| q > best_q).unwrap_or(true) {
+                best = Some((locale, q));
+            }
+        }
+        best.map(|(locale, _)| locale)
+    }
+}
+
+/// The text of the UI in one language. Formats are for `chrono`'s `format`.
+#[derive(Serialize)]
+pub struct Messages {
+    /// Name of the language in itself, shown in the language setting.
+    pub language_name: &'static str,
+    pub date_format: &'static str,
+    pub datetime_format: &'static str,
+    /// Tells which part of a series an article is, with `{part}` and `{parts}`.
+    pub series_part_format: &'static str,
+
+    pub posted_on: &'static str,
+    pub read_more: &'static str,
+    pub pinned: &'static str,
+    pub featured_articles: &'static str,
+    pub previous_article: &'static str,
+    pub next_article: &'static str,
+    pub related_articles: &'static str,
+    pub series: &'static str,
+    pub previous_page: &'static str,
+    pub next_page: &'static str,
+    pub not_found: &'static str,
+    pub internal_error: &'static str,
+
+    pub admin: &'static str,
+    pub login: &'static str,
+    pub logout: &'static str,
+    pub username: &'static str,
+    pub password: &'static str,
+    pub login_succeeded: &'static str,
+    pub login_failed: &'static str,
+    pub logged_out: &'static str,
+    pub back_to_top: &'static str,
+
+    pub article_list: &'static str,
+    pub title: &'static str,
+    pub created_at: &'static str,
+    pub updated_at: &'static str,
+    pub draft: &'static str,
+    pub edit: &'static str,
+    pub delete: &'static str,
+    pub create: &'static str,
+    pub new_article: &'static str,
+    pub show_article: &'static str,
+    pub create_article: &'static str,
+    pub edit_article: &'static str,
+    pub delete_article: &'static str,
+    pub title_label: &'static str,
+    pub body_label: &'static str,
+    pub summary_label: &'static str,
+    pub title_required: &'static str,
+    pub title_too_long: &'static str,
+    pub body_too_large: &'static str,
+    pub summary_too_long: &'static str,
+    pub fix_errors: &'static str,
+    pub article_created: &'static str,
+    pub article_updated: &'static str,
+    pub article_deleted: &'static str,
+    pub confirm_delete: &'static str,
+    pub featured: &'static str,
+    pub pin: &'static str,
+    pub unpin: &'static str,
+    pub feature: &'static str,
+    pub unfeature: &'static str,
+    pub autosave_found: &'static str,
+    pub restore: &'static str,
+    pub discard: &'static str,
+    pub autosaved: &'static str,
+    pub autosave_failed: &'static str,
+    pub autosave_restored: &'static str,
+    pub autosave_discarded: &'static str,
+    pub edit_conflict: &'static str,
+    pub conflict_found: &'static str,
+    pub conflict_help: &'static str,
+    pub differences: &'static str,
+    pub current_version: &'static str,
+    pub your_version: &'static str,
+
+    pub site_settings: &'static str,
+    pub site_name_label: &'static str,
+    pub description_label: &'static str,
+    pub base_url_label: &'static str,
+    pub timezone_label: &'static str,
+    pub posts_per_page_label: &'static str,
+    pub footer_label: &'static str,
+    pub locale_label: &'static str,
+    pub save: &'static str,
+    pub site_name_required: &'static str,
+    pub invalid_base_url: &'static str,
+    pub invalid_timezone: &'static str,
+    pub invalid_posts_per_page: &'static str,
+    pub invalid_locale: &'static str,
+    pub settings_saved: &'static str,
+
+    pub series_list: &'static str,
+    pub new_series: &'static str,
+    pub create_series: &'static str,
+    pub edit_series: &'static str,
+    pub delete_series: &'static str,
+    pub slug_label: &'static str,
+    pub series_parts: &'static str,
+    pub article_label: &'static str,
+    pub add_part: &'static str,
+    pub move_up: &'static str,
+    pub move_down: &'static str,
+    pub remove_part: &'static str,
+    pub no_parts: &'static str,
+    pub invalid_slug: &'static str,
+    pub slug_taken: &'static str,
+    pub series_created: &'static str,
+    pub series_updated: &'static str,
+    pub series_deleted: &'static str,
+    pub parts_updated: &'static str,
+    pub confirm_delete_series: &'static str,
+    pub audit_log: &'static str,
+    pub action_label: &'static str,
+    pub actor_label: &'static str,
+    pub all_actions: &'static str,
+    pub all_actors: &'static str,
+    pub filter: &'static str,
+    pub no_audit_entries: &'static str,
+    pub date: &'static str,
+    pub actor: &'static str,
+    pub action: &'static str,
+    pub target: &'static str,
+    pub ip_address: &'static str,
+    pub audit_login: &'static str,
+    pub audit_login_failed: &'static str,
+    pub audit_logout: &'static str,
+    pub audit_create_article: &'static str,
+    pub audit_update_article: &'static str,
+    pub audit_delete_article: &'static str,
+    pub audit_promote_article: &'static str,
+    pub audit_update_settings: &'static str,
+    pub audit_create_series: &'static str,
+    pub audit_update_series: &'static str,
+    pub audit_delete_series: &'static str,
+}
+
+impl Messages {
+    /// Which part of a series an article is, e.g. "Part 2 of 5".
+    pub fn series_part(&self, part: usize, parts: usize) -> String {
+        self.series_part_format
+            .replace("{part}", &part.to_string())
+            .replace("{parts}", &parts.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiate() {
+        assert_eq!(Locale::negotiate("ja,en-US;q=0.9,en;q=0.8"), Some(Locale::Ja));
+        assert_eq!(Locale::negotiate("en-US,en;q=0.9,ja;q=0.8"), Some(Locale::En));
+        assert_eq!(Locale::negotiate("fr-FR,fr;q=0.9,ja;q=0.5,en;q=0.7"), Some(Locale::En));
+        assert_eq!(Locale::negotiate("ja;q=0,en;q=0.1"), Some(Locale::En));
+        assert_eq!(Locale::negotiate("en, ja"), Some(Locale::En));
+        assert_eq!(Locale::negotiate("fr"), None);
+        assert_eq!(Locale::negotiate(""), None);
+    }
+
+    #[test]
+    fn series_part() {
+        assert_eq!(Locale::En.messages().series_part(2, 5), "Part 2 of 5");
+        assert_eq!(Locale::Ja.messages().series_part(2, 5), "全5回中の第2回");
+    }
+}
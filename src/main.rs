@@ -1,45 +1,79 @@
 #![feature(custom_derive)]
 #![feature(plugin)]
 #![feature(proc_macro)]
+#![feature(proc_macro_non_items)]
 #![plugin(rocket_codegen)]
 #![recursion_limit = "1024"]
 
 extern crate ammonia;
+extern crate brotli;
 extern crate chrono;
 #[macro_use]
 extern crate error_chain;
+extern crate flate2;
+extern crate handlebars;
+#[macro_use]
+extern crate lazy_static;
 extern crate maud;
 extern crate openssl;
+extern crate png;
 extern crate postgres;
 extern crate pulldown_cmark;
 extern crate r2d2;
 extern crate r2d2_postgres;
 extern crate rocket;
+extern crate rusttype;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate serde_yaml;
 extern crate sha2;
-
-#[cfg(test)]
+extern crate syntect;
 extern crate toml;
+extern crate xml;
 
+mod archive;
+mod commands;
+mod compression;
 mod db;
 mod errors;
+mod generate;
+mod import;
+mod locale;
 mod views;
 
 use rocket::fairing::AdHoc;
 use rocket::Rocket;
 
+use std::env;
+use std::process;
+
 fn rocket() -> Rocket {
-    rocket::ignite()
+    let rocket = rocket::ignite();
+    let compression = compression::Compression::from_config(rocket.config()).unwrap();
+    rocket
+        .attach(compression)
         .attach(AdHoc::on_attach(|rocket| {
             let pool = {
                 let database_url = rocket.config().get_str("database_url").unwrap();
                 db::init_pool(database_url).unwrap()
             };
-            Ok(rocket.manage(pool))
+            let site = {
+                let conn = pool.get().unwrap();
+                views::Site::load(rocket.config(), &*conn).unwrap()
+            };
+            let cards = views::card::Cards::from_site(&site).unwrap();
+            let site = views::SharedSite::new(site);
+            Ok(rocket.manage(pool).manage(site).manage(cards))
         }))
         .mount("/", routes![
             views::static_file,
             views::index_page,
+            views::index_page_n,
             views::article_page,
+            views::series_page,
+            views::card::og_image,
             views::admin::login_page,
             views::admin::login,
             views::admin::logout,
@@ -48,15 +82,41 @@ fn rocket() -> Rocket {
             views::admin::home_page,
             views::admin::preview_article_page,
             views::admin::create_article_page,
+            views::admin::restore_new_article_page,
             views::admin::create_article,
             views::admin::update_article_page,
+            views::admin::restore_article_page,
             views::admin::update_article,
+            views::admin::autosave_new_article,
+            views::admin::autosave_article,
+            views::admin::discard_new_article_autosave,
+            views::admin::discard_article_autosave,
+            views::admin::preview,
             views::admin::delete_article_page,
             views::admin::delete_article,
+            views::admin::update_promotion,
+            views::admin::settings_page,
+            views::admin::update_settings,
+            views::admin::series_list_page,
+            views::admin::create_series_page,
+            views::admin::create_series,
+            views::admin::update_series_page,
+            views::admin::update_series,
+            views::admin::update_series_parts,
+            views::admin::delete_series_page,
+            views::admin::delete_series,
+            views::admin::filtered_audit_log_page,
+            views::admin::audit_log_page,
         ])
         .catch(errors![views::not_found, views::internal_error])
 }
 
 fn main() {
-    rocket().launch();
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() {
+        rocket().launch();
+    } else if let Err(e) = commands::run(&args) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
 }
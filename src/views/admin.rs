@@ -1,15 +1,25 @@
-use super::{Markdown, APP_NAME};
+use super::{CurrentSite, Markdown, SharedSite, Site};
+use super::diff::{self, Line};
+use super::validation::{self, FieldError, FieldErrors};
 use db::Connection;
 use db::article::Article;
+use db::audit::{self, Action, Entry};
+use db::autosave::Autosave;
 use db::person::Person;
+use db::series::{self, Series};
+use db::setting::{Settings, Value};
 use errors::*;
+use locale::{Locale, Messages};
 
+use chrono::{DateTime, FixedOffset, Local};
 use maud::{html, Markup, Render, DOCTYPE};
-use rocket::http::{Cookie, Cookies};
+use postgres::GenericConnection;
+use rocket::http::{Cookie, Cookies, Status};
 use rocket::outcome::IntoOutcome;
-use rocket::Request;
+use rocket::{Request, State};
 use rocket::request::{FlashMessage, Form, FromRequest, Outcome};
-use rocket::response::{Flash, Redirect};
+use rocket::response::{self, status, Flash, Redirect, Responder};
+use std::cmp;
 use std::path::PathBuf;
 
 #[derive(FromForm)]
@@ -33,11 +43,68 @@ impl<'a, 'r> FromRequest<'a, 'r> for LoginUser {
     }
 }
 
-pub struct ArticleView<'a>(&'a Article);
+/// The address a request came from, recorded in the audit log.
+pub struct ClientIp(Option<String>);
+
+impl ClientIp {
+    fn get(&self) -> Option<&str> {
+        self.0.as_ref().map(|ip| &ip[..])
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for ClientIp {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> Outcome<ClientIp, ()> {
+        // Behind a reverse proxy every connection comes from the proxy, which
+        // passes the client's address on in a header.
+        let forwarded = request.guard::<CurrentSite>().succeeded().and_then(|site| {
+            site.client_ip_header
+                .as_ref()
+                .and_then(|name| request.headers().get_one(name))
+                .map(|ip| ip.trim().to_owned())
+        });
+        let ip = forwarded.or_else(|| request.remote().map(|addr| addr.ip().to_string()));
+        ::rocket::Outcome::Success(ClientIp(ip))
+    }
+}
+
+/// Language of the admin: the one the browser prefers, or the site's when it
+/// prefers none of the supported ones.
+pub struct AdminLocale(Locale);
+
+impl AdminLocale {
+    fn messages(&self) -> &'static Messages {
+        self.0.messages()
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for AdminLocale {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> Outcome<AdminLocale, ()> {
+        let preferred = request
+            .headers()
+            .get_one("Accept-Language")
+            .and_then(Locale::negotiate);
+        let locale = match preferred {
+            Some(locale) => locale,
+            None => request
+                .guard::<CurrentSite>()
+                .succeeded()
+                .map(|site| site.settings.locale)
+                .unwrap_or_default(),
+        };
+        ::rocket::Outcome::Success(AdminLocale(locale))
+    }
+}
+
+pub struct ArticleView<'a>(&'a Article, &'a Site, &'a Messages);
 
 impl<'a> Render for ArticleView<'a> {
     fn render(&self) -> Markup {
         let article = self.0;
+        let created_at = article.created_at.with_timezone(&self.1.settings.timezone);
         html! {
             article {
                 h1 {
@@ -45,41 +112,330 @@ impl<'a> Render for ArticleView<'a> {
                 }
                 footer {
                     span {
-                        "Posted on "
-                        time datetime={ (article.created_at.format("%F")) } {
-                            (article.created_at.format("%Y年%-m月%-d日"))
+                        (self.2.posted_on)
+                        time datetime={ (created_at.format("%F")) } {
+                            (created_at.format(self.2.date_format))
                         }
                     }
                 }
-                section { p (Markdown(&article.body)) }
+                section { p (Markdown(&article.body, self.1.extensions)) }
             }
         }
     }
 }
 
+/// Maximum number of characters of an article title.
+const MAX_TITLE_LENGTH: usize = 200;
+/// Maximum size of an article body in KiB, well within the `forms` limit of the
+/// request data in `Rocket.toml`.
+const MAX_BODY_KB: usize = 512;
+/// Maximum number of characters of an article summary.
+const MAX_SUMMARY_LENGTH: usize = 1000;
+
 #[derive(FromForm)]
 pub struct ArticleForm {
     title: String,
     body: String,
+    summary: String,
+    /// When the article being updated was loaded into the editor, in RFC 3339.
+    updated_at: Option<String>,
+}
+
+impl ArticleForm {
+    fn summary(&self) -> Option<&str> {
+        if self.summary.trim().is_empty() {
+            None
+        } else {
+            Some(&self.summary)
+        }
+    }
+
+    /// Checks the fields, returning the errors found.
+    fn validate(&self, t: &Messages) -> FieldErrors {
+        let mut errors = FieldErrors::default();
+        errors.check("title", validation::present(&self.title), t.title_required);
+        errors.check(
+            "title",
+            validation::max_chars(&self.title, MAX_TITLE_LENGTH),
+            validation::with_max(t.title_too_long, MAX_TITLE_LENGTH),
+        );
+        errors.check(
+            "body",
+            self.body.len() <= MAX_BODY_KB * 1024,
+            validation::with_max(t.body_too_large, MAX_BODY_KB),
+        );
+        errors.check(
+            "summary",
+            validation::max_chars(&self.summary, MAX_SUMMARY_LENGTH),
+            validation::with_max(t.summary_too_long, MAX_SUMMARY_LENGTH),
+        );
+        errors
+    }
+
+    fn updated_at(&self) -> Option<DateTime<Local>> {
+        self.updated_at
+            .as_ref()
+            .and_then(|updated_at| DateTime::parse_from_rfc3339(updated_at).ok())
+            .map(|updated_at| updated_at.with_timezone(&Local))
+    }
+}
+
+/// The response to saving an article: a redirect once it is saved, or a page to
+/// fix the submitted article in.
+pub enum EditorResponse {
+    Saved(Flash<Redirect>),
+    Page(Status, Markup),
+}
+
+impl<'r> Responder<'r> for EditorResponse {
+    fn respond_to(self, request: &Request) -> response::Result<'r> {
+        match self {
+            EditorResponse::Saved(redirect) => redirect.respond_to(request),
+            EditorResponse::Page(code, page) => status::Custom(code, page).respond_to(request),
+        }
+    }
+}
+
+/// Whether an article is pinned to the top of the index and featured.
+#[derive(FromForm)]
+pub struct PromotionForm {
+    pinned: bool,
+    featured: bool,
+}
+
+/// A button setting whether an article is pinned and featured.
+struct PromotionButton<'a>(&'a Article, bool, bool, &'a str);
+
+impl<'a> Render for PromotionButton<'a> {
+    fn render(&self) -> Markup {
+        html! {
+            form action={ "/admin/article/promotion/" (self.0.id) } method="post" {
+                input type="hidden" name="_method" value="put";
+                input type="hidden" name="pinned" value=(self.1);
+                input type="hidden" name="featured" value=(self.2);
+                button type="submit" (self.3)
+            }
+        }
+    }
+}
+
+/// URL the editor of an article, or of a new one, autosaves to.
+fn autosave_url(id: Option<i32>) -> String {
+    match id {
+        Some(id) => format!("/admin/article/autosave/{}", id),
+        None => "/admin/article/autosave".to_owned(),
+    }
+}
+
+/// The form to write an article in, with a preview of the body. Its contents are
+/// autosaved while it is open.
+struct ArticleEditor<'a> {
+    /// `None` for a new article.
+    id: Option<i32>,
+    title: &'a str,
+    body: &'a str,
+    summary: &'a str,
+    /// When the article being updated was loaded.
+    updated_at: Option<&'a DateTime<Local>>,
+    /// What to fix in the submitted article.
+    errors: Option<&'a FieldErrors>,
+    site: &'a Site,
+    t: &'a Messages,
+}
+
+impl<'a> Render for ArticleEditor<'a> {
+    fn render(&self) -> Markup {
+        let t = self.t;
+        let action = match self.id {
+            Some(id) => format!("/admin/article/update/{}", id),
+            None => "/admin/article/create".to_owned(),
+        };
+        html! {
+            form#article action=(action) method="post" data-autosave=(autosave_url(self.id)) {
+                @if self.id.is_some() {
+                    input type="hidden" name="_method" value="put";
+                }
+                @if let Some(updated_at) = self.updated_at {
+                    input type="hidden" name="updated_at" value=(updated_at.to_rfc3339());
+                }
+                label for="title" (t.title_label)
+                @if self.id.is_some() {
+                    input#title type="text" name="title" value=(self.title);
+                } @else {
+                    input#title type="text" name="title" value=(self.title) autofocus="autofocus";
+                }
+                (FieldError(self.errors, "title"))
+                label for="body" (t.body_label)
+                div.editor {
+                    textarea#body name="body" (self.body)
+                    div#preview.preview data-url="/admin/preview" {
+                        (Markdown(self.body, self.site.extensions))
+                    }
+                }
+                (FieldError(self.errors, "body"))
+                label for="summary" (t.summary_label)
+                textarea#summary name="summary" (self.summary)
+                (FieldError(self.errors, "summary"))
+                @if self.id.is_some() {
+                    button type="submit" (t.edit)
+                } @else {
+                    button type="submit" (t.create)
+                }
+                p#autosave-status data-failed=(t.autosave_failed) {}
+            }
+            script src=(self.site.assets.url("/js/editor.js")) {}
+        }
+    }
+}
+
+/// Offers to restore or discard the contents autosaved from an editor.
+struct AutosaveNotice<'a>(&'a Autosave, Option<i32>, &'a Site, &'a Messages);
+
+impl<'a> Render for AutosaveNotice<'a> {
+    fn render(&self) -> Markup {
+        let t = self.3;
+        let saved_at = self.0.saved_at.with_timezone(&self.2.settings.timezone);
+        let restore = match self.1 {
+            Some(id) => format!("/admin/article/update/{}/restore", id),
+            None => "/admin/article/create/restore".to_owned(),
+        };
+        html! {
+            div#autosave-notice {
+                p {
+                    (t.autosave_found)
+                    time datetime=(saved_at.to_rfc3339()) (saved_at.format(t.datetime_format))
+                }
+                a href=(restore) (t.restore)
+                form action=(autosave_url(self.1)) method="post" {
+                    input type="hidden" name="_method" value="delete";
+                    button type="submit" (t.discard)
+                }
+            }
+        }
+    }
+}
+
+#[derive(FromForm)]
+pub struct PreviewForm {
+    body: String,
 }
 
-struct AdminHeader<'a>(&'a str);
+#[derive(FromForm)]
+pub struct SettingsForm {
+    site_name: String,
+    description: String,
+    base_url: String,
+    timezone: String,
+    posts_per_page: String,
+    footer: String,
+    locale: String,
+}
+
+impl SettingsForm {
+    /// Converts the form into settings, or returns a message telling what to fix.
+    fn settings(&self, t: &Messages) -> ::std::result::Result<Settings, &'static str> {
+        let site_name = self.site_name.trim();
+        if site_name.is_empty() {
+            return Err(t.site_name_required);
+        }
+        let base_url = self.base_url.trim().trim_right_matches('/');
+        if !base_url.is_empty() && !base_url.starts_with("http://")
+            && !base_url.starts_with("https://")
+        {
+            return Err(t.invalid_base_url);
+        }
+        let timezone = match FixedOffset::parse(&self.timezone) {
+            Ok(timezone) => timezone,
+            Err(_) => return Err(t.invalid_timezone),
+        };
+        let posts_per_page = match self.posts_per_page.trim().parse::<i64>() {
+            Ok(n) if n >= 1 => n,
+            _ => return Err(t.invalid_posts_per_page),
+        };
+        let locale = match Locale::from_code(&self.locale) {
+            Some(locale) => locale,
+            None => return Err(t.invalid_locale),
+        };
+        Ok(Settings {
+            site_name: site_name.to_owned(),
+            description: self.description.trim().to_owned(),
+            base_url: base_url.to_owned(),
+            timezone: timezone,
+            posts_per_page: posts_per_page,
+            footer: self.footer.trim().to_owned(),
+            locale: locale,
+            updated_at: None,
+        })
+    }
+}
+
+#[derive(FromForm)]
+pub struct SeriesForm {
+    slug: String,
+    title: String,
+    description: String,
+}
+
+impl SeriesForm {
+    /// Returns a message telling what to fix, if anything. Whether the slug is
+    /// taken is left to the caller.
+    fn validate(&self, t: &Messages) -> ::std::result::Result<(), &'static str> {
+        if self.title.trim().is_empty() {
+            return Err(t.title_required);
+        }
+        if !series::is_valid_slug(self.slug.trim()) {
+            return Err(t.invalid_slug);
+        }
+        Ok(())
+    }
+}
+
+/// A change to the articles of a series. `action` is `add`, `up`, `down` or
+/// `remove`.
+#[derive(FromForm)]
+pub struct PartForm {
+    article: i32,
+    action: String,
+}
+
+impl PartForm {
+    /// Applies the change to the article ids of a series in order. Returns whether
+    /// anything changed.
+    fn apply(&self, ids: &mut Vec<i32>) -> bool {
+        let position = ids.iter().position(|&id| id == self.article);
+        match (self.action.as_str(), position) {
+            ("add", None) => ids.push(self.article),
+            ("up", Some(i)) if i > 0 => ids.swap(i - 1, i),
+            ("down", Some(i)) if i + 1 < ids.len() => ids.swap(i, i + 1),
+            ("remove", Some(i)) => {
+                ids.remove(i);
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+struct AdminHeader<'a>(&'a Site, &'a Messages, &'a str);
 
 impl<'a> Render for AdminHeader<'a> {
     fn render(&self) -> Markup {
+        let site_name = &self.0.settings.site_name;
+        let t = self.1;
         html! {
             (DOCTYPE)
             meta charset="utf-8";
             meta content="width=device-width" name="viewport";
-            @if self.0.is_empty() {
-                title { (APP_NAME) " (管理)" }
+            @if self.2.is_empty() {
+                title { (site_name) " (" (t.admin) ")" }
             } else {
-                title { (self.0) " - " (APP_NAME) " (管理)" }
+                title { (self.2) " - " (site_name) " (" (t.admin) ")" }
             }
-            link href="/css/admin.css" rel="stylesheet";
+            link href=(self.0.assets.url("/css/admin.css")) rel="stylesheet";
+            link href=(self.0.assets.url("/css/highlight.css")) rel="stylesheet";
             header {
                 h1 {
-                    a href="/admin/" { (APP_NAME) " (管理)" }
+                    a href="/admin/" { (site_name) " (" (t.admin) ")" }
                 }
             }
         }
@@ -87,16 +443,17 @@ impl<'a> Render for AdminHeader<'a> {
 }
 
 #[get("/admin/login")]
-pub fn login_page(flash: Option<FlashMessage>) -> Markup {
+pub fn login_page(flash: Option<FlashMessage>, site: CurrentSite, locale: AdminLocale) -> Markup {
+    let t = locale.messages();
     html! {
-        (AdminHeader("ログイン"))
+        (AdminHeader(&site, t, t.login))
         main {
             form#login action="login" method="post" {
-                label for="username" "ユーザー名:"
+                label for="username" (t.username)
                 input#username type="text" name="username" autofocus="autofocus";
-                label for="password" "パスワード:"
+                label for="password" (t.password)
                 input#password type="password" name="password";
-                button type="submit" "ログイン"
+                button type="submit" (t.login)
                 @if let Some(ref msg) = flash {
                     p class={ (msg.name()) "-message" } (msg.msg())
                 }
@@ -106,30 +463,42 @@ pub fn login_page(flash: Option<FlashMessage>) -> Markup {
 }
 
 #[post("/admin/login", data = "<form>")]
-pub fn login(mut cookies: Cookies, form: Form<LoginForm>, conn: Connection) -> Flash<Redirect> {
+pub fn login(
+    mut cookies: Cookies,
+    form: Form<LoginForm>,
+    conn: Connection,
+    ip: ClientIp,
+    locale: AdminLocale,
+) -> Result<Flash<Redirect>> {
     let login = form.get();
+    let t = locale.messages();
     match Person::find(&*conn, &login.username, &login.password) {
         Ok(user) => {
+            Entry::record(&*conn, Action::Login, Some(user.id), "", None, ip.get())?;
             cookies.add_private(Cookie::new("user_id", user.id.to_string()));
-            Flash::success(
-                Redirect::to("/admin"),
-                "ログインに成功しました。",
-            )
-        }
-        Err(_) => Flash::error(
-            Redirect::to("/admin/login"),
-            "無効なユーザー名またはパスワードが指定されました。",
-        ),
+            Ok(Flash::success(Redirect::to("/admin"), t.login_succeeded))
+        }
+        Err(_) => {
+            // The name tried is kept to tell guessing from a mistyped password.
+            Entry::record(&*conn, Action::LoginFailed, None, &login.username, None, ip.get())?;
+            Ok(Flash::error(Redirect::to("/admin/login"), t.login_failed))
+        }
     }
 }
 
 #[post("/admin/logout")]
-pub fn logout(mut cookies: Cookies) -> Flash<Redirect> {
+pub fn logout(
+    login_user: Option<LoginUser>,
+    mut cookies: Cookies,
+    conn: Connection,
+    ip: ClientIp,
+    locale: AdminLocale,
+) -> Result<Flash<Redirect>> {
+    if let Some(login_user) = login_user {
+        Entry::record(&*conn, Action::Logout, Some(login_user.0), "", None, ip.get())?;
+    }
     cookies.remove_private(Cookie::named("user_id"));
-    Flash::success(
-        Redirect::to("/admin/login"),
-        "ログアウトしました。",
-    )
+    Ok(Flash::success(Redirect::to("/admin/login"), locale.messages().logged_out))
 }
 
 #[get("/admin", rank = 2)]
@@ -147,41 +516,67 @@ pub fn home_page(
     _login_user: LoginUser,
     flash: Option<FlashMessage>,
     conn: Connection,
+    site: CurrentSite,
+    locale: AdminLocale,
 ) -> Result<Markup> {
     let articles = Article::list(&*conn)?;
+    let t = locale.messages();
+    let timezone = site.settings.timezone;
 
     Ok(html! {
-        (AdminHeader(""))
+        (AdminHeader(&site, t, ""))
         main {
             @if !articles.is_empty() {
-                h1 "記事の一覧"
+                h1 (t.article_list)
                 table {
                     thead {
                         tr {
-                            th.title "タイトル"
-                            th.created-at "登録日時"
-                            th.updated-at "更新日時"
+                            th.title (t.title)
+                            th.created-at (t.created_at)
+                            th.updated-at (t.updated_at)
+                            th.pinned (t.pinned)
+                            th.featured (t.featured)
                             th colspan="2";
                         }
                     }
                     tbody {
                         @for article in articles {
                             tr {
-                                td.title { a href={ "/admin/article/" (article.id) } (article.title) }
-                                td.created-at (article.created_at.format("%F %T"))
-                                td.updated-at (article.updated_at.format("%F %T"))
-                                td.update { a href={ "/admin/article/update/" (article.id) } "編集" }
-                                td.delete { a href={ "/admin/article/delete/" (article.id) } "削除" }
+                                td.title {
+                                    a href={ "/admin/article/" (article.id) } (article.title)
+                                    @if article.draft {
+                                        span.draft (t.draft)
+                                    }
+                                }
+                                td.created-at {
+                                    (article.created_at.with_timezone(&timezone).format(t.datetime_format))
+                                }
+                                td.updated-at {
+                                    (article.updated_at.with_timezone(&timezone).format(t.datetime_format))
+                                }
+                                td.pinned {
+                                    (PromotionButton(&article, !article.pinned, article.featured,
+                                                     if article.pinned { t.unpin } else { t.pin }))
+                                }
+                                td.featured {
+                                    (PromotionButton(&article, article.pinned, !article.featured,
+                                                     if article.featured { t.unfeature } else { t.feature }))
+                                }
+                                td.update { a href={ "/admin/article/update/" (article.id) } (t.edit) }
+                                td.delete { a href={ "/admin/article/delete/" (article.id) } (t.delete) }
                             }
                         }
                     }
                 }
             }
-            a href="/admin/article/create" "記事を作成する"
+            a href="/admin/article/create" (t.new_article)
+            a href="/admin/series" (t.series_list)
+            a href="/admin/settings" (t.site_settings)
+            a href="/admin/audit" (t.audit_log)
         }
         footer {
             form action="/admin/logout" method="post" {
-                button type="submit" "ログアウト"
+                button type="submit" (t.logout)
             }
             @if let Some(ref msg) = flash {
                 p class={ (msg.name()) "-message" } (msg.msg())
@@ -196,46 +591,47 @@ pub fn preview_article_page(
     id: i32,
     flash: Option<FlashMessage>,
     conn: Connection,
+    site: CurrentSite,
+    locale: AdminLocale,
 ) -> Result<Markup> {
     let article = Article::get(&*conn, id)?;
+    let t = locale.messages();
     Ok(html!{
-        (AdminHeader("記事の表示"))
+        (AdminHeader(&site, t, t.show_article))
         main {
-            h1 "記事の表示"
-            (ArticleView(&article))
-            a href={ "/admin/article/update/" (article.id) } "編集"
-            a href={ "/admin/article/delete/" (article.id) } "削除"
+            h1 (t.show_article)
+            (ArticleView(&article, &site, t))
+            a href={ "/admin/article/update/" (article.id) } (t.edit)
+            a href={ "/admin/article/delete/" (article.id) } (t.delete)
             @if let Some(ref msg) = flash {
                 p class={ (msg.name()) "-message" } (msg.msg())
             }
         }
         footer {
-            a href="/admin" "トップに戻る"
+            a href="/admin" (t.back_to_top)
         }
     })
 }
 
 #[get("/admin/article/create")]
-pub fn create_article_page(_login_user: LoginUser, flash: Option<FlashMessage>) -> Markup {
-    html! {
-        (AdminHeader("記事の作成"))
-        main {
-            h1 "記事の作成"
-            form#article action="/admin/article/create" method="post" {
-                label for="title" "タイトル:"
-                input#title type="text" name="title" autofocus="autofocus";
-                label for="body" "本文:"
-                textarea#body name="body" {}
-                button type="submit" "作成"
-            }
-            @if let Some(ref msg) = flash {
-                p class={ (msg.name()) "-message" } (msg.msg())
-            }
-        }
-        footer {
-            a href="/admin" "トップに戻る"
-        }
-    }
+pub fn create_article_page(
+    login_user: LoginUser,
+    flash: Option<FlashMessage>,
+    conn: Connection,
+    site: CurrentSite,
+    locale: AdminLocale,
+) -> Result<Markup> {
+    editor_page(&*conn, login_user.0, None, flash, &site, locale.messages(), false)
+}
+
+#[get("/admin/article/create/restore")]
+pub fn restore_new_article_page(
+    login_user: LoginUser,
+    conn: Connection,
+    site: CurrentSite,
+    locale: AdminLocale,
+) -> Result<Markup> {
+    editor_page(&*conn, login_user.0, None, None, &site, locale.messages(), true)
 }
 
 #[post("/admin/article/create", data = "<form>")]
@@ -243,110 +639,1172 @@ pub fn create_article(
     login_user: LoginUser,
     form: Form<ArticleForm>,
     conn: Connection,
-) -> Result<Flash<Redirect>> {
+    ip: ClientIp,
+    site: CurrentSite,
+    locale: AdminLocale,
+) -> Result<EditorResponse> {
     let article = form.get();
-    if article.title.is_empty() {
-        Ok(Flash::warning(
-            Redirect::to("/admin/article/create"),
-            "タイトルを入力してください。",
-        ))
-    } else {
-        let tx = conn.transaction()?;
-        let person = Person::get(&tx, login_user.0)?;
-        Article::create(&tx, &article.title, &article.body, &person)?;
-        tx.commit()?;
-        Ok(Flash::success(
-            Redirect::to("/admin"),
-            "記事が作成されました。",
-        ))
+    let t = locale.messages();
+    let errors = article.validate(t);
+    if !errors.is_empty() {
+        let page = invalid_article_page(None, article, &errors, &site, t);
+        return Ok(EditorResponse::Page(Status::UnprocessableEntity, page));
     }
+    let tx = conn.transaction()?;
+    let person = Person::get(&tx, login_user.0)?;
+    let created = Article::create(
+        &tx,
+        &article.title,
+        &article.body,
+        article.summary(),
+        &person,
+    )?;
+    Entry::record(
+        &tx,
+        Action::CreateArticle,
+        Some(login_user.0),
+        &created.title,
+        Some(created.id),
+        ip.get(),
+    )?;
+    Autosave::discard(&tx, login_user.0, None)?;
+    tx.commit()?;
+    Ok(EditorResponse::Saved(Flash::success(
+        Redirect::to("/admin"),
+        t.article_created,
+    )))
 }
 
 #[get("/admin/article/update/<id>")]
 pub fn update_article_page(
-    _login_user: LoginUser,
+    login_user: LoginUser,
     id: i32,
     flash: Option<FlashMessage>,
     conn: Connection,
+    site: CurrentSite,
+    locale: AdminLocale,
 ) -> Result<Markup> {
     let article = Article::get(&*conn, id)?;
+    editor_page(&*conn, login_user.0, Some(&article), flash, &site, locale.messages(), false)
+}
+
+#[get("/admin/article/update/<id>/restore")]
+pub fn restore_article_page(
+    login_user: LoginUser,
+    id: i32,
+    conn: Connection,
+    site: CurrentSite,
+    locale: AdminLocale,
+) -> Result<Markup> {
+    let article = Article::get(&*conn, id)?;
+    editor_page(&*conn, login_user.0, Some(&article), None, &site, locale.messages(), true)
+}
+
+/// The page to create an article, or to update `article`. The editor is filled with
+/// the contents autosaved for the person if `restore` is set, and otherwise offers
+/// to restore them.
+fn editor_page(
+    conn: &GenericConnection,
+    person: i32,
+    article: Option<&Article>,
+    flash: Option<FlashMessage>,
+    site: &Site,
+    t: &Messages,
+    restore: bool,
+) -> Result<Markup> {
+    let id = article.map(|article| article.id);
+    let heading = if id.is_some() {
+        t.edit_article
+    } else {
+        t.create_article
+    };
+    let saved = Autosave::find(conn, person, id)?;
+    let (title, body, summary) = match (&saved, article) {
+        (&Some(ref saved), _) if restore => (&saved.title[..], &saved.body[..], &saved.summary[..]),
+        (_, Some(article)) => (
+            &article.title[..],
+            &article.body[..],
+            article.summary.as_ref().map(|summary| &summary[..]).unwrap_or(""),
+        ),
+        (_, None) => ("", "", ""),
+    };
+    // Autosaved contents equal to the article's are left over from saving it.
+    let unsaved = match saved {
+        Some(ref saved)
+            if !restore
+                && (&saved.title[..], &saved.body[..], &saved.summary[..])
+                    != (title, body, summary) =>
+        {
+            Some(saved)
+        }
+        _ => None,
+    };
     Ok(html! {
-        (AdminHeader("記事の編集"))
+        (AdminHeader(site, t, heading))
         main {
-            h1 "記事の編集"
-            form#article action={ "/admin/article/update/" (id) } method="post" {
-                input type="hidden" name="_method" value="put";
-                label for="title" "タイトル:"
-                input#title type="text" name="title" value=(article.title);
-                label for="body" "本文:"
-                textarea#body name="body" (article.body)
-                button type="submit" "編集"
+            h1 (heading)
+            @if let Some(saved) = unsaved {
+                (AutosaveNotice(saved, id, site, t))
+            }
+            @if restore && saved.is_some() {
+                p.success-message (t.autosave_restored)
             }
+            (ArticleEditor {
+                id: id,
+                title: title,
+                body: body,
+                summary: summary,
+                updated_at: article.map(|article| &article.updated_at),
+                errors: None,
+                site: site,
+                t: t,
+            })
             @if let Some(ref msg) = flash {
                 p class={ (msg.name()) "-message" } (msg.msg())
             }
         }
         footer {
-            a href="/admin" "トップに戻る"
+            a href="/admin" (t.back_to_top)
         }
     })
 }
 
 #[put("/admin/article/update/<id>", data = "<form>")]
 pub fn update_article(
-    _login_user: LoginUser,
+    login_user: LoginUser,
     id: i32,
     form: Form<ArticleForm>,
     conn: Connection,
-) -> Result<Flash<Redirect>> {
+    ip: ClientIp,
+    site: CurrentSite,
+    locale: AdminLocale,
+) -> Result<Option<EditorResponse>> {
+    if Article::revision(&*conn, id)?.is_none() {
+        return Ok(None);
+    }
     let article = form.get();
-    if article.title.is_empty() {
-        Ok(Flash::warning(
-            Redirect::to(&format!("/admin/article/update/{}", id)),
-            "タイトルを入力してください。",
-        ))
+    let t = locale.messages();
+    let errors = article.validate(t);
+    if !errors.is_empty() {
+        let page = invalid_article_page(Some(id), article, &errors, &site, t);
+        return Ok(Some(EditorResponse::Page(Status::UnprocessableEntity, page)));
+    }
+    let tx = conn.transaction()?;
+    let updated = match article.updated_at() {
+        Some(updated_at) => Article::update(
+            &tx,
+            id,
+            &article.title,
+            &article.body,
+            article.summary(),
+            &updated_at,
+        )?,
+        // Without the revision the article was loaded at, changes made since then
+        // cannot be ruled out, so it is treated as a conflict.
+        None => false,
+    };
+    if !updated {
+        let current = match Article::revision(&tx, id)? {
+            Some(_) => Article::get(&tx, id)?,
+            None => return Ok(None),
+        };
+        let page = conflict_page(&current, article, &site, t);
+        return Ok(Some(EditorResponse::Page(Status::Conflict, page)));
+    }
+    Entry::record(
+        &tx,
+        Action::UpdateArticle,
+        Some(login_user.0),
+        &article.title,
+        Some(id),
+        ip.get(),
+    )?;
+    Autosave::discard(&tx, login_user.0, Some(id))?;
+    tx.commit()?;
+    Ok(Some(EditorResponse::Saved(Flash::success(
+        Redirect::to(&format!("/admin/article/{}", id)),
+        t.article_updated,
+    ))))
+}
+
+/// The editor again with the article that was submitted and what to fix in it.
+fn invalid_article_page(
+    id: Option<i32>,
+    article: &ArticleForm,
+    errors: &FieldErrors,
+    site: &Site,
+    t: &Messages,
+) -> Markup {
+    let heading = if id.is_some() {
+        t.edit_article
     } else {
-        let tx = conn.transaction()?;
-        Article::update(&tx, id, &article.title, &article.body)?;
-        tx.commit()?;
-        Ok(Flash::success(
-            Redirect::to(&format!("/admin/article/{}", id)),
-            "記事が編集されました。",
-        ))
+        t.create_article
+    };
+    // The revision the article was loaded at is kept for the check of conflicts.
+    let updated_at = article.updated_at();
+    html! {
+        (AdminHeader(site, t, heading))
+        main {
+            h1 (heading)
+            p.warning-message (t.fix_errors)
+            (ArticleEditor {
+                id: id,
+                title: &article.title,
+                body: &article.body,
+                summary: &article.summary,
+                updated_at: updated_at.as_ref(),
+                errors: Some(errors),
+                site: site,
+                t: t,
+            })
+        }
+        footer {
+            a href="/admin" (t.back_to_top)
+        }
+    }
+}
+
+/// Shown instead of saving an article that someone else has saved since it was
+/// loaded into the editor. The editor keeps the submitted article, to be merged with
+/// the current one and saved again over it.
+fn conflict_page(current: &Article, mine: &ArticleForm, site: &Site, t: &Messages) -> Markup {
+    let updated_at = current.updated_at.with_timezone(&site.settings.timezone);
+    let current_summary = current
+        .summary
+        .as_ref()
+        .map(|summary| &summary[..])
+        .unwrap_or("");
+    html! {
+        (AdminHeader(site, t, t.edit_conflict))
+        main {
+            h1 (t.edit_conflict)
+            p.warning-message {
+                (t.conflict_found)
+                time datetime=(updated_at.to_rfc3339()) (updated_at.format(t.datetime_format))
+            }
+            p (t.conflict_help)
+            section#conflict {
+                h2 (t.differences)
+                @if current.title != mine.title {
+                    p.diff-title {
+                        del (current.title)
+                        ins (mine.title)
+                    }
+                }
+                pre.diff {
+                    @for line in diff::lines(&current.body, &mine.body) {
+                        @match line {
+                            Line::Same(line) => {
+                                span.same { "  " (line) "\n" }
+                            }
+                            Line::Removed(line) => {
+                                del { "- " (line) "\n" }
+                            }
+                            Line::Added(line) => {
+                                ins { "+ " (line) "\n" }
+                            }
+                        }
+                    }
+                }
+                h2 (t.current_version)
+                textarea#current readonly="readonly" (current.body)
+                @if current_summary != mine.summary {
+                    textarea#current-summary readonly="readonly" (current_summary)
+                }
+            }
+            h2 (t.your_version)
+            (ArticleEditor {
+                id: Some(current.id),
+                title: &mine.title,
+                body: &mine.body,
+                summary: &mine.summary,
+                updated_at: Some(&current.updated_at),
+                errors: None,
+                site: site,
+                t: t,
+            })
+        }
+        footer {
+            a href="/admin" (t.back_to_top)
+        }
     }
 }
 
+#[put("/admin/article/autosave", data = "<form>")]
+pub fn autosave_new_article(
+    login_user: LoginUser,
+    form: Form<ArticleForm>,
+    conn: Connection,
+    site: CurrentSite,
+    locale: AdminLocale,
+) -> Result<String> {
+    autosave(&*conn, login_user.0, None, form.get(), &site, locale.messages())
+}
+
+#[put("/admin/article/autosave/<id>", data = "<form>")]
+pub fn autosave_article(
+    login_user: LoginUser,
+    id: i32,
+    form: Form<ArticleForm>,
+    conn: Connection,
+    site: CurrentSite,
+    locale: AdminLocale,
+) -> Result<String> {
+    autosave(&*conn, login_user.0, Some(id), form.get(), &site, locale.messages())
+}
+
+/// Saves the contents of the editor. Returns a message telling when, for the
+/// editor to show.
+fn autosave(
+    conn: &GenericConnection,
+    person: i32,
+    id: Option<i32>,
+    form: &ArticleForm,
+    site: &Site,
+    t: &Messages,
+) -> Result<String> {
+    let saved_at = Autosave::save(conn, person, id, &form.title, &form.body, &form.summary)?;
+    Ok(format!(
+        "{}{}",
+        t.autosaved,
+        saved_at.with_timezone(&site.settings.timezone).format("%T")
+    ))
+}
+
+#[delete("/admin/article/autosave")]
+pub fn discard_new_article_autosave(
+    login_user: LoginUser,
+    conn: Connection,
+    locale: AdminLocale,
+) -> Result<Flash<Redirect>> {
+    Autosave::discard(&*conn, login_user.0, None)?;
+    Ok(Flash::success(
+        Redirect::to("/admin/article/create"),
+        locale.messages().autosave_discarded,
+    ))
+}
+
+#[delete("/admin/article/autosave/<id>")]
+pub fn discard_article_autosave(
+    login_user: LoginUser,
+    id: i32,
+    conn: Connection,
+    locale: AdminLocale,
+) -> Result<Flash<Redirect>> {
+    Autosave::discard(&*conn, login_user.0, Some(id))?;
+    Ok(Flash::success(
+        Redirect::to(&format!("/admin/article/update/{}", id)),
+        locale.messages().autosave_discarded,
+    ))
+}
+
+/// Renders Markdown posted from the editor for its preview pane.
+#[post("/admin/preview", data = "<form>")]
+pub fn preview(_login_user: LoginUser, form: Form<PreviewForm>, site: CurrentSite) -> Markup {
+    Markdown(&form.get().body, site.extensions).render()
+}
+
 #[get("/admin/article/delete/<id>")]
-pub fn delete_article_page(_login_user: LoginUser, id: i32, conn: Connection) -> Result<Markup> {
+pub fn delete_article_page(
+    _login_user: LoginUser,
+    id: i32,
+    conn: Connection,
+    site: CurrentSite,
+    locale: AdminLocale,
+) -> Result<Markup> {
     let article = Article::get(&*conn, id)?;
+    let t = locale.messages();
     Ok(html! {
-        (AdminHeader("記事の削除"))
+        (AdminHeader(&site, t, t.delete_article))
         main {
-            h1 "記事の削除"
-            (ArticleView(&article))
-            p "記事を削除します。よろしいですか？"
+            h1 (t.delete_article)
+            (ArticleView(&article, &site, t))
+            p (t.confirm_delete)
             form action={ "/admin/article/delete/" (id) } method="post" {
                 input type="hidden" name="_method" value="delete";
-                button type="submit" "削除"
+                button type="submit" (t.delete)
             }
         }
         footer {
-            a href="/admin" "トップに戻る"
+            a href="/admin" (t.back_to_top)
         }
     })
 }
 
 #[delete("/admin/article/delete/<id>")]
 pub fn delete_article(
-    _login_user: LoginUser,
+    login_user: LoginUser,
     id: i32,
     conn: Connection,
+    ip: ClientIp,
+    locale: AdminLocale,
 ) -> Result<Flash<Redirect>> {
     let tx = conn.transaction()?;
+    let article = Article::get(&tx, id)?;
     Article::delete(&tx, id)?;
+    Entry::record(
+        &tx,
+        Action::DeleteArticle,
+        Some(login_user.0),
+        &article.title,
+        Some(id),
+        ip.get(),
+    )?;
     tx.commit()?;
     Ok(Flash::success(
         Redirect::to("/admin"),
-        "記事が削除されました。",
+        locale.messages().article_deleted,
     ))
 }
+
+#[put("/admin/article/promotion/<id>", data = "<form>")]
+pub fn update_promotion(
+    login_user: LoginUser,
+    id: i32,
+    form: Form<PromotionForm>,
+    conn: Connection,
+    ip: ClientIp,
+    locale: AdminLocale,
+) -> Result<Flash<Redirect>> {
+    let promotion = form.get();
+    let tx = conn.transaction()?;
+    Article::promote(&tx, id, promotion.pinned, promotion.featured)?;
+    let article = Article::get(&tx, id)?;
+    Entry::record(
+        &tx,
+        Action::PromoteArticle,
+        Some(login_user.0),
+        &article.title,
+        Some(id),
+        ip.get(),
+    )?;
+    tx.commit()?;
+    Ok(Flash::success(
+        Redirect::to("/admin"),
+        locale.messages().article_updated,
+    ))
+}
+
+#[get("/admin/settings")]
+pub fn settings_page(
+    _login_user: LoginUser,
+    flash: Option<FlashMessage>,
+    site: CurrentSite,
+    locale: AdminLocale,
+) -> Markup {
+    let settings = &site.settings;
+    let t = locale.messages();
+    html! {
+        (AdminHeader(&site, t, t.site_settings))
+        main {
+            h1 (t.site_settings)
+            form#settings action="/admin/settings" method="post" {
+                input type="hidden" name="_method" value="put";
+                label for="site_name" (t.site_name_label)
+                input#site_name type="text" name="site_name" value=(settings.site_name);
+                label for="description" (t.description_label)
+                textarea#description name="description" (settings.description)
+                label for="base_url" (t.base_url_label)
+                input#base_url type="url" name="base_url" value=(settings.base_url)
+                    placeholder="https://example.com";
+                label for="timezone" (t.timezone_label)
+                input#timezone type="text" name="timezone" value=(settings.timezone.format())
+                    placeholder="+09:00";
+                label for="posts_per_page" (t.posts_per_page_label)
+                input#posts_per_page type="number" name="posts_per_page" min="1"
+                    value=(settings.posts_per_page);
+                label for="footer" (t.footer_label)
+                textarea#footer name="footer" (settings.footer)
+                label for="locale" (t.locale_label)
+                select#locale name="locale" {
+                    @for locale in Locale::ALL {
+                        @if *locale == settings.locale {
+                            option value=(locale.code()) selected="selected" {
+                                (locale.messages().language_name)
+                            }
+                        } @else {
+                            option value=(locale.code()) (locale.messages().language_name)
+                        }
+                    }
+                }
+                button type="submit" (t.save)
+            }
+            @if let Some(ref msg) = flash {
+                p class={ (msg.name()) "-message" } (msg.msg())
+            }
+        }
+        footer {
+            a href="/admin" (t.back_to_top)
+        }
+    }
+}
+
+#[put("/admin/settings", data = "<form>")]
+pub fn update_settings(
+    login_user: LoginUser,
+    form: Form<SettingsForm>,
+    conn: Connection,
+    ip: ClientIp,
+    shared: State<SharedSite>,
+    locale: AdminLocale,
+) -> Result<Flash<Redirect>> {
+    let t = locale.messages();
+    let settings = match form.get().settings(t) {
+        Ok(settings) => settings,
+        Err(message) => return Ok(Flash::warning(Redirect::to("/admin/settings"), message)),
+    };
+    let tx = conn.transaction()?;
+    settings.save(&tx)?;
+    Entry::record(&tx, Action::UpdateSettings, Some(login_user.0), "", None, ip.get())?;
+    let settings = Settings::load(&tx, settings)?;
+    tx.commit()?;
+    shared.set_settings(settings);
+    Ok(Flash::success(Redirect::to("/admin/settings"), t.settings_saved))
+}
+
+#[get("/admin/series")]
+pub fn series_list_page(
+    _login_user: LoginUser,
+    flash: Option<FlashMessage>,
+    conn: Connection,
+    site: CurrentSite,
+    locale: AdminLocale,
+) -> Result<Markup> {
+    let series_list = Series::list(&*conn)?;
+    let t = locale.messages();
+    let timezone = site.settings.timezone;
+    Ok(html! {
+        (AdminHeader(&site, t, t.series_list))
+        main {
+            @if !series_list.is_empty() {
+                h1 (t.series_list)
+                table {
+                    thead {
+                        tr {
+                            th.title (t.title)
+                            th.updated-at (t.updated_at)
+                            th colspan="2";
+                        }
+                    }
+                    tbody {
+                        @for series in series_list {
+                            tr {
+                                td.title {
+                                    a href={ "/series/" (series.slug) } (series.title)
+                                }
+                                td.updated-at {
+                                    (series.updated_at.with_timezone(&timezone).format(t.datetime_format))
+                                }
+                                td.update { a href={ "/admin/series/update/" (series.id) } (t.edit) }
+                                td.delete { a href={ "/admin/series/delete/" (series.id) } (t.delete) }
+                            }
+                        }
+                    }
+                }
+            }
+            a href="/admin/series/create" (t.new_series)
+            @if let Some(ref msg) = flash {
+                p class={ (msg.name()) "-message" } (msg.msg())
+            }
+        }
+        footer {
+            a href="/admin" (t.back_to_top)
+        }
+    })
+}
+
+#[get("/admin/series/create")]
+pub fn create_series_page(
+    _login_user: LoginUser,
+    flash: Option<FlashMessage>,
+    site: CurrentSite,
+    locale: AdminLocale,
+) -> Markup {
+    let t = locale.messages();
+    html! {
+        (AdminHeader(&site, t, t.create_series))
+        main {
+            h1 (t.create_series)
+            form#series action="/admin/series/create" method="post" {
+                label for="title" (t.title_label)
+                input#title type="text" name="title" autofocus="autofocus";
+                label for="slug" (t.slug_label)
+                input#slug type="text" name="slug" pattern="[a-z0-9-]+";
+                label for="description" (t.description_label)
+                textarea#description name="description" {}
+                button type="submit" (t.create)
+            }
+            @if let Some(ref msg) = flash {
+                p class={ (msg.name()) "-message" } (msg.msg())
+            }
+        }
+        footer {
+            a href="/admin/series" (t.series_list)
+        }
+    }
+}
+
+#[post("/admin/series/create", data = "<form>")]
+pub fn create_series(
+    login_user: LoginUser,
+    form: Form<SeriesForm>,
+    conn: Connection,
+    ip: ClientIp,
+    locale: AdminLocale,
+) -> Result<Flash<Redirect>> {
+    let form = form.get();
+    let t = locale.messages();
+    let back = Redirect::to("/admin/series/create");
+    if let Err(message) = form.validate(t) {
+        return Ok(Flash::warning(back, message));
+    }
+    let tx = conn.transaction()?;
+    if Series::find_by_slug(&tx, form.slug.trim())?.is_some() {
+        return Ok(Flash::warning(back, t.slug_taken));
+    }
+    let series = Series::create(
+        &tx,
+        form.slug.trim(),
+        form.title.trim(),
+        form.description.trim(),
+    )?;
+    Entry::record(
+        &tx,
+        Action::CreateSeries,
+        Some(login_user.0),
+        &series.title,
+        Some(series.id),
+        ip.get(),
+    )?;
+    tx.commit()?;
+    Ok(Flash::success(
+        Redirect::to(&format!("/admin/series/update/{}", series.id)),
+        t.series_created,
+    ))
+}
+
+#[get("/admin/series/update/<id>")]
+pub fn update_series_page(
+    _login_user: LoginUser,
+    id: i32,
+    flash: Option<FlashMessage>,
+    conn: Connection,
+    site: CurrentSite,
+    locale: AdminLocale,
+) -> Result<Markup> {
+    let series = Series::get(&*conn, id)?;
+    let parts = Series::all_parts(&*conn, id)?;
+    let candidates: Vec<Article> = Article::list(&*conn)?
+        .into_iter()
+        .filter(|article| parts.iter().all(|&(ref part, _)| part.id != article.id))
+        .collect();
+    let t = locale.messages();
+    let parts_action = format!("/admin/series/parts/{}", id);
+    Ok(html! {
+        (AdminHeader(&site, t, t.edit_series))
+        main {
+            h1 (t.edit_series)
+            form#series action={ "/admin/series/update/" (id) } method="post" {
+                input type="hidden" name="_method" value="put";
+                label for="title" (t.title_label)
+                input#title type="text" name="title" value=(series.title);
+                label for="slug" (t.slug_label)
+                input#slug type="text" name="slug" pattern="[a-z0-9-]+" value=(series.slug);
+                label for="description" (t.description_label)
+                textarea#description name="description" (series.description)
+                button type="submit" (t.save)
+            }
+            @if let Some(ref msg) = flash {
+                p class={ (msg.name()) "-message" } (msg.msg())
+            }
+            h2 (t.series_parts)
+            @if parts.is_empty() {
+                p (t.no_parts)
+            } @else {
+                table#parts {
+                    tbody {
+                        @for (i, &(ref part, draft)) in parts.iter().enumerate() {
+                            tr {
+                                td.position ((i + 1))
+                                td.title {
+                                    a href={ "/admin/article/" (part.id) } (part.title)
+                                    @if draft {
+                                        span.draft (t.draft)
+                                    }
+                                }
+                                td.actions {
+                                    form action=(parts_action) method="post" {
+                                        input type="hidden" name="article" value=(part.id);
+                                        button type="submit" name="action" value="up" (t.move_up)
+                                        button type="submit" name="action" value="down" (t.move_down)
+                                        button type="submit" name="action" value="remove" (t.remove_part)
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            @if !candidates.is_empty() {
+                form#add-part action=(parts_action) method="post" {
+                    input type="hidden" name="action" value="add";
+                    label for="article" (t.article_label)
+                    select#article name="article" {
+                        @for article in &candidates {
+                            option value=(article.id) (article.title)
+                        }
+                    }
+                    button type="submit" (t.add_part)
+                }
+            }
+        }
+        footer {
+            a href="/admin/series" (t.series_list)
+        }
+    })
+}
+
+#[put("/admin/series/update/<id>", data = "<form>")]
+pub fn update_series(
+    login_user: LoginUser,
+    id: i32,
+    form: Form<SeriesForm>,
+    conn: Connection,
+    ip: ClientIp,
+    locale: AdminLocale,
+) -> Result<Flash<Redirect>> {
+    let form = form.get();
+    let t = locale.messages();
+    let back = Redirect::to(&format!("/admin/series/update/{}", id));
+    if let Err(message) = form.validate(t) {
+        return Ok(Flash::warning(back, message));
+    }
+    let tx = conn.transaction()?;
+    if let Some(other) = Series::find_by_slug(&tx, form.slug.trim())? {
+        if other.id != id {
+            return Ok(Flash::warning(back, t.slug_taken));
+        }
+    }
+    Series::update(
+        &tx,
+        id,
+        form.slug.trim(),
+        form.title.trim(),
+        form.description.trim(),
+    )?;
+    Entry::record(
+        &tx,
+        Action::UpdateSeries,
+        Some(login_user.0),
+        form.title.trim(),
+        Some(id),
+        ip.get(),
+    )?;
+    tx.commit()?;
+    Ok(Flash::success(back, t.series_updated))
+}
+
+#[post("/admin/series/parts/<id>", data = "<form>")]
+pub fn update_series_parts(
+    login_user: LoginUser,
+    id: i32,
+    form: Form<PartForm>,
+    conn: Connection,
+    ip: ClientIp,
+    locale: AdminLocale,
+) -> Result<Flash<Redirect>> {
+    let back = Redirect::to(&format!("/admin/series/update/{}", id));
+    let tx = conn.transaction()?;
+    let mut ids: Vec<i32> = Series::all_parts(&tx, id)?
+        .into_iter()
+        .map(|(part, _)| part.id)
+        .collect();
+    if form.get().apply(&mut ids) {
+        Series::set_articles(&tx, id, &ids)?;
+        let series = Series::get(&tx, id)?;
+        Entry::record(
+            &tx,
+            Action::UpdateSeries,
+            Some(login_user.0),
+            &series.title,
+            Some(id),
+            ip.get(),
+        )?;
+        tx.commit()?;
+    }
+    Ok(Flash::success(back, locale.messages().parts_updated))
+}
+
+#[get("/admin/series/delete/<id>")]
+pub fn delete_series_page(
+    _login_user: LoginUser,
+    id: i32,
+    conn: Connection,
+    site: CurrentSite,
+    locale: AdminLocale,
+) -> Result<Markup> {
+    let series = Series::get(&*conn, id)?;
+    let t = locale.messages();
+    Ok(html! {
+        (AdminHeader(&site, t, t.delete_series))
+        main {
+            h1 (t.delete_series)
+            h2 (series.title)
+            p (t.confirm_delete_series)
+            form action={ "/admin/series/delete/" (id) } method="post" {
+                input type="hidden" name="_method" value="delete";
+                button type="submit" (t.delete)
+            }
+        }
+        footer {
+            a href="/admin/series" (t.series_list)
+        }
+    })
+}
+
+#[delete("/admin/series/delete/<id>")]
+pub fn delete_series(
+    login_user: LoginUser,
+    id: i32,
+    conn: Connection,
+    ip: ClientIp,
+    locale: AdminLocale,
+) -> Result<Flash<Redirect>> {
+    let tx = conn.transaction()?;
+    let series = Series::get(&tx, id)?;
+    Series::delete(&tx, id)?;
+    Entry::record(
+        &tx,
+        Action::DeleteSeries,
+        Some(login_user.0),
+        &series.title,
+        Some(id),
+        ip.get(),
+    )?;
+    tx.commit()?;
+    Ok(Flash::success(
+        Redirect::to("/admin/series"),
+        locale.messages().series_deleted,
+    ))
+}
+
+/// Number of audit log entries on a page.
+const AUDIT_LOG_PAGE_SIZE: i64 = 50;
+
+/// Filter and page of the audit log, from the query string.
+#[derive(FromForm)]
+pub struct AuditQuery {
+    action: Option<String>,
+    actor: Option<String>,
+    page: Option<i64>,
+}
+
+impl AuditQuery {
+    fn filter(&self) -> audit::Filter {
+        audit::Filter {
+            action: self.action.as_ref().and_then(|code| Action::from_code(code)),
+            actor: match self.actor {
+                Some(ref actor) if !actor.is_empty() => Some(&actor[..]),
+                _ => None,
+            },
+        }
+    }
+
+    /// The URL of `page` of the entries matching the same filter.
+    fn page_url(&self, page: i64) -> String {
+        let filter = self.filter();
+        let mut url = format!("/admin/audit?page={}", page);
+        if let Some(action) = filter.action {
+            url.push_str("&action=");
+            url.push_str(action.code());
+        }
+        if let Some(actor) = filter.actor {
+            url.push_str("&actor=");
+            url.push_str(&query_value(actor));
+        }
+        url
+    }
+}
+
+/// Percent-encodes a value of a query string.
+fn query_value(value: &str) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'...b'Z' | b'a'...b'z' | b'0'...b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn action_label(action: Action, t: &Messages) -> &'static str {
+    match action {
+        Action::Login => t.audit_login,
+        Action::LoginFailed => t.audit_login_failed,
+        Action::Logout => t.audit_logout,
+        Action::CreateArticle => t.audit_create_article,
+        Action::UpdateArticle => t.audit_update_article,
+        Action::DeleteArticle => t.audit_delete_article,
+        Action::PromoteArticle => t.audit_promote_article,
+        Action::UpdateSettings => t.audit_update_settings,
+        Action::CreateSeries => t.audit_create_series,
+        Action::UpdateSeries => t.audit_update_series,
+        Action::DeleteSeries => t.audit_delete_series,
+    }
+}
+
+#[get("/admin/audit?<query>")]
+pub fn filtered_audit_log_page(
+    _login_user: LoginUser,
+    query: AuditQuery,
+    conn: Connection,
+    site: CurrentSite,
+    locale: AdminLocale,
+) -> Result<Markup> {
+    audit_log_page_for(&*conn, &query, &site, locale.messages())
+}
+
+#[get("/admin/audit", rank = 1)]
+pub fn audit_log_page(
+    _login_user: LoginUser,
+    conn: Connection,
+    site: CurrentSite,
+    locale: AdminLocale,
+) -> Result<Markup> {
+    let query = AuditQuery {
+        action: None,
+        actor: None,
+        page: None,
+    };
+    audit_log_page_for(&*conn, &query, &site, locale.messages())
+}
+
+fn audit_log_page_for(
+    conn: &GenericConnection,
+    query: &AuditQuery,
+    site: &Site,
+    t: &Messages,
+) -> Result<Markup> {
+    let filter = query.filter();
+    let count = Entry::count(conn, &filter)?;
+    let pages = cmp::max(1, (count + AUDIT_LOG_PAGE_SIZE - 1) / AUDIT_LOG_PAGE_SIZE);
+    let page = cmp::max(1, cmp::min(query.page.unwrap_or(1), pages));
+    let offset = (page - 1) * AUDIT_LOG_PAGE_SIZE;
+    let entries = Entry::list(conn, &filter, offset, AUDIT_LOG_PAGE_SIZE)?;
+    let actors = Entry::actors(conn)?;
+    let timezone = site.settings.timezone;
+    Ok(html! {
+        (AdminHeader(site, t, t.audit_log))
+        main {
+            h1 (t.audit_log)
+            form#audit-filter action="/admin/audit" method="get" {
+                label for="action" (t.action_label)
+                select#action name="action" {
+                    option value="" (t.all_actions)
+                    @for action in Action::ALL {
+                        @if filter.action == Some(*action) {
+                            option value=(action.code()) selected="selected" (action_label(*action, t))
+                        } @else {
+                            option value=(action.code()) (action_label(*action, t))
+                        }
+                    }
+                }
+                label for="actor" (t.actor_label)
+                select#actor name="actor" {
+                    option value="" (t.all_actors)
+                    @for actor in &actors {
+                        @if filter.actor == Some(&actor[..]) {
+                            option value=(actor) selected="selected" (actor)
+                        } @else {
+                            option value=(actor) (actor)
+                        }
+                    }
+                }
+                button type="submit" (t.filter)
+            }
+            @if entries.is_empty() {
+                p (t.no_audit_entries)
+            } @else {
+                table#audit {
+                    thead {
+                        tr {
+                            th.created-at (t.date)
+                            th.actor (t.actor)
+                            th.action (t.action)
+                            th.target (t.target)
+                            th.ip (t.ip_address)
+                        }
+                    }
+                    tbody {
+                        @for entry in &entries {
+                            tr {
+                                td.created-at {
+                                    (entry.created_at.with_timezone(&timezone).format(t.datetime_format))
+                                }
+                                td.actor (entry.actor)
+                                td.action (action_label(entry.action, t))
+                                td.target {
+                                    (entry.target)
+                                    @if let Some(id) = entry.target_id {
+                                        span.id { "#" (id) }
+                                    }
+                                }
+                                td.ip (entry.ip.as_ref().map(|ip| &ip[..]).unwrap_or(""))
+                            }
+                        }
+                    }
+                }
+            }
+            @if pages > 1 {
+                nav.pagination {
+                    @if page > 1 {
+                        a.prev href=(query.page_url(page - 1)) rel="prev" (t.previous_page)
+                    }
+                    span.page { (page) " / " (pages) }
+                    @if page < pages {
+                        a.next href=(query.page_url(page + 1)) rel="next" (t.next_page)
+                    }
+                }
+            }
+        }
+        footer {
+            a href="/admin" (t.back_to_top)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn form() -> SettingsForm {
+        SettingsForm {
+            site_name: " blog ".to_owned(),
+            description: String::new(),
+            base_url: "https://example.com/".to_owned(),
+            timezone: "+09:00".to_owned(),
+            posts_per_page: "5".to_owned(),
+            footer: String::new(),
+            locale: "en".to_owned(),
+        }
+    }
+
+    #[test]
+    fn settings_form() {
+        let t = Locale::En.messages();
+        let settings = form().settings(t).unwrap();
+        assert_eq!(settings.site_name, "blog");
+        assert_eq!(settings.base_url, "https://example.com");
+        assert_eq!(settings.posts_per_page, 5);
+        assert_eq!(settings.locale, Locale::En);
+
+        let mut invalid = form();
+        invalid.site_name = " ".to_owned();
+        assert_eq!(invalid.settings(t).err(), Some(t.site_name_required));
+        let mut invalid = form();
+        invalid.base_url = "example.com".to_owned();
+        assert_eq!(invalid.settings(t).err(), Some(t.invalid_base_url));
+        let mut invalid = form();
+        invalid.timezone = "JST".to_owned();
+        assert_eq!(invalid.settings(t).err(), Some(t.invalid_timezone));
+        let mut invalid = form();
+        invalid.posts_per_page = "0".to_owned();
+        assert_eq!(invalid.settings(t).err(), Some(t.invalid_posts_per_page));
+        let mut invalid = form();
+        invalid.locale = "fr".to_owned();
+        assert_eq!(invalid.settings(t).err(), Some(t.invalid_locale));
+    }
+
+    fn article_form() -> ArticleForm {
+        ArticleForm {
+            title: "title".to_owned(),
+            body: "body".to_owned(),
+            summary: String::new(),
+            updated_at: Some("2018-02-24T12:34:56.789012+09:00".to_owned()),
+        }
+    }
+
+    #[test]
+    fn validate_article_form() {
+        let t = Locale::En.messages();
+        assert!(article_form().validate(t).is_empty());
+        assert!(article_form().updated_at().is_some());
+        let mut form = article_form();
+        form.updated_at = Some("yesterday".to_owned());
+        assert!(form.updated_at().is_none());
+
+        let mut invalid = article_form();
+        invalid.title = " ".to_owned();
+        invalid.summary = "要".repeat(MAX_SUMMARY_LENGTH + 1);
+        let errors = invalid.validate(t);
+        assert_eq!(errors.get("title"), Some(t.title_required));
+        assert_eq!(errors.get("body"), None);
+        assert_eq!(
+            errors.get("summary"),
+            Some(&validation::with_max(t.summary_too_long, MAX_SUMMARY_LENGTH)[..])
+        );
+
+        let mut invalid = article_form();
+        invalid.title = "題".repeat(MAX_TITLE_LENGTH + 1);
+        invalid.body = "a".repeat(MAX_BODY_KB * 1024 + 1);
+        let errors = invalid.validate(t);
+        assert_eq!(
+            errors.get("title"),
+            Some(&validation::with_max(t.title_too_long, MAX_TITLE_LENGTH)[..])
+        );
+        assert_eq!(
+            errors.get("body"),
+            Some(&validation::with_max(t.body_too_large, MAX_BODY_KB)[..])
+        );
+        // Limits are in characters, not bytes.
+        invalid.title = "題".repeat(MAX_TITLE_LENGTH);
+        assert_eq!(invalid.validate(t).get("title"), None);
+    }
+
+    #[test]
+    fn audit_query() {
+        let query = AuditQuery {
+            action: Some("article_delete".to_owned()),
+            actor: Some("a&b".to_owned()),
+            page: Some(2),
+        };
+        let filter = query.filter();
+        assert_eq!(filter.action, Some(Action::DeleteArticle));
+        assert_eq!(filter.actor, Some("a&b"));
+        assert_eq!(query.page_url(3), "/admin/audit?page=3&action=article_delete&actor=a%26b");
+
+        let query = AuditQuery {
+            action: Some(String::new()),
+            actor: Some(String::new()),
+            page: None,
+        };
+        let filter = query.filter();
+        assert_eq!((filter.action, filter.actor), (None, None));
+        assert_eq!(query.page_url(1), "/admin/audit?page=1");
+    }
+
+    #[test]
+    fn part_form() {
+        let part = |article, action: &str| PartForm {
+            article: article,
+            action: action.to_owned(),
+        };
+        let mut ids = vec![1, 2];
+        assert!(part(3, "add").apply(&mut ids));
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert!(!part(3, "add").apply(&mut ids));
+        assert!(part(3, "up").apply(&mut ids));
+        assert_eq!(ids, vec![1, 3, 2]);
+        assert!(!part(1, "up").apply(&mut ids));
+        assert!(part(1, "down").apply(&mut ids));
+        assert_eq!(ids, vec![3, 1, 2]);
+        assert!(!part(2, "down").apply(&mut ids));
+        assert!(part(1, "remove").apply(&mut ids));
+        assert_eq!(ids, vec![3, 2]);
+        assert!(!part(1, "remove").apply(&mut ids));
+        assert!(!part(2, "rename").apply(&mut ids));
+    }
+}
@@ -0,0 +1,197 @@
+//! Content hashed ("fingerprinted") URLs of the files under `static/` and the
+//! theme's static directory, which can be cached forever since any change to a file
+//! changes its URL.
+
+use errors::*;
+
+use sha2::{Digest, Sha256};
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// `Cache-Control` of fingerprinted URLs.
+pub const IMMUTABLE: &'static str = "public, max-age=31536000, immutable";
+
+/// The files of one or more directories served at the root of the site. A file in
+/// an earlier directory hides the one with the same path in later directories.
+#[derive(Debug, Default)]
+pub struct Assets {
+    dirs: Vec<PathBuf>,
+    /// Content hash of each file by its path relative to the directories.
+    hashes: HashMap<String, String>,
+    version: String,
+}
+
+impl Assets {
+    /// Hashes the files of `dirs`, which are computed once at startup. Missing
+    /// directories are skipped.
+    pub fn load(dirs: Vec<PathBuf>) -> Result<Assets> {
+        let mut assets = Assets::default();
+        for dir in &dirs {
+            if dir.is_dir() {
+                assets.add_dir(dir, "")?;
+            }
+        }
+        assets.dirs = dirs;
+        assets.version = assets.compute_version();
+        Ok(assets)
+    }
+
+    /// Hashes the directories, which include the theme's, and the hashes of all files.
+    fn compute_version(&self) -> String {
+        let mut files = self.hashes.iter().collect::<Vec<_>>();
+        files.sort();
+        let mut hasher = Sha256::default();
+        for dir in &self.dirs {
+            hasher.input(dir.to_string_lossy().as_bytes());
+            hasher.input(b"\n");
+        }
+        for (path, hash) in files {
+            hasher.input(format!("{} {}\n", path, hash).as_bytes());
+        }
+        short_hash(&hasher.result())
+    }
+
+    fn add_dir(&mut self, dir: &Path, prefix: &str) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+            if entry.file_type()?.is_dir() {
+                self.add_dir(&entry.path(), &format!("{}/", name))?;
+            } else if !self.hashes.contains_key(&name) {
+                let mut data = Vec::new();
+                File::open(entry.path())?.read_to_end(&mut data)?;
+                self.hashes.insert(name, short_hash(&Sha256::digest(&data)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the fingerprinted URL of a file, e.g. `/css/style.0123abcd.css` for
+    /// `/css/style.css`. Unknown paths are returned as they are.
+    pub fn url(&self, path: &str) -> String {
+        match self.hashes.get(path.trim_left_matches('/')) {
+            Some(hash) => fingerprint(path, hash),
+            None => path.to_owned(),
+        }
+    }
+
+    /// Maps a fingerprinted path back to the file it names, if its hash is current.
+    pub fn resolve(&self, path: &Path) -> Option<PathBuf> {
+        let path = path.to_str()?;
+        let (dir, file_name) = match path.rfind('/') {
+            Some(i) => path.split_at(i + 1),
+            None => ("", path),
+        };
+        let mut parts = file_name.rsplitn(3, '.');
+        let (original, hash) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(ext), Some(hash), Some(stem)) => (format!("{}{}.{}", dir, stem, ext), hash),
+            (Some(hash), Some(stem), None) => (format!("{}{}", dir, stem), hash),
+            _ => return None,
+        };
+        match self.hashes.get(&original) {
+            Some(current) if current == hash => Some(PathBuf::from(original)),
+            _ => None,
+        }
+    }
+
+    /// Finds the file at a relative path in the first directory that has it.
+    pub fn find(&self, path: &Path) -> Option<PathBuf> {
+        self.dirs
+            .iter()
+            .map(|dir| dir.join(path))
+            .find(|path| path.is_file())
+    }
+
+    /// Changes whenever a file or the theme does, so that pages linking to the
+    /// files can tell their revisions apart.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn dirs(&self) -> &[PathBuf] {
+        &self.dirs
+    }
+
+    /// Lists each file with its fingerprinted path.
+    pub fn files(&self) -> Vec<(PathBuf, PathBuf)> {
+        self.hashes
+            .iter()
+            .map(|(path, hash)| (PathBuf::from(path), PathBuf::from(fingerprint(path, hash))))
+            .collect()
+    }
+}
+
+/// Shortens a digest to the hex of its first bytes, enough to tell versions apart.
+pub fn short_hash(digest: &[u8]) -> String {
+    digest.iter().take(4).map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Inserts the hash before the extension of a file name.
+fn fingerprint(path: &str, hash: &str) -> String {
+    let name_start = path.rfind('/').map(|i| i + 1).unwrap_or(0);
+    match path[name_start..].rfind('.') {
+        Some(i) if i > 0 => {
+            let (stem, ext) = path.split_at(name_start + i);
+            format!("{}.{}{}", stem, hash, ext)
+        }
+        _ => format!("{}.{}", path, hash),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{env, process};
+
+    #[test]
+    fn theme_overrides() {
+        let dir = env::temp_dir().join(format!("zakki-assets-{}", process::id()));
+        let (theme, base) = (dir.join("theme"), dir.join("static"));
+        for &(root, content) in &[(&theme, "theme"), (&base, "base")] {
+            fs::create_dir_all(root.join("css")).unwrap();
+            File::create(root.join("css/style.css"))
+                .and_then(|mut file| file.write_all(content.as_bytes()))
+                .unwrap();
+        }
+        File::create(base.join("favicon.ico")).unwrap();
+
+        let assets = Assets::load(vec![theme.clone(), base.clone(), dir.join("none")]).unwrap();
+        assert_eq!(assets.find(Path::new("css/style.css")), Some(theme.join("css/style.css")));
+        assert_eq!(assets.find(Path::new("favicon.ico")), Some(base.join("favicon.ico")));
+        assert_eq!(assets.find(Path::new("missing.png")), None);
+        let theme_only = Assets::load(vec![theme.clone()]).unwrap();
+        assert_eq!(assets.url("/css/style.css"), theme_only.url("/css/style.css"));
+        assert!(!assets.version().is_empty());
+        assert_ne!(assets.version(), theme_only.version());
+        let reloaded = Assets::load(vec![theme.clone(), base.clone(), dir.join("none")]).unwrap();
+        assert_eq!(assets.version(), reloaded.version());
+        File::create(base.join("favicon.ico"))
+            .and_then(|mut file| file.write_all(b"icon"))
+            .unwrap();
+        let changed = Assets::load(vec![theme.clone(), base.clone(), dir.join("none")]).unwrap();
+        assert_ne!(assets.version(), changed.version());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn fingerprinted_urls() {
+        let mut assets = Assets::default();
+        assets.hashes.insert("css/style.css".to_owned(), "0123abcd".to_owned());
+        assets.hashes.insert("LICENSE".to_owned(), "89ef4567".to_owned());
+        assert_eq!(assets.url("/css/style.css"), "/css/style.0123abcd.css");
+        assert_eq!(assets.url("/LICENSE"), "/LICENSE.89ef4567");
+        assert_eq!(assets.url("/css/unknown.css"), "/css/unknown.css");
+
+        assert_eq!(
+            assets.resolve(Path::new("css/style.0123abcd.css")),
+            Some(PathBuf::from("css/style.css"))
+        );
+        assert_eq!(assets.resolve(Path::new("LICENSE.89ef4567")), Some(PathBuf::from("LICENSE")));
+        assert_eq!(assets.resolve(Path::new("css/style.00000000.css")), None);
+        assert_eq!(assets.resolve(Path::new("css/style.css")), None);
+    }
+}
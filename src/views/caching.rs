@@ -0,0 +1,157 @@
+//! Conditional requests and caching headers for public responses.
+
+use errors::*;
+
+use chrono::{DateTime, TimeZone, Utc};
+use rocket::Outcome;
+use rocket::http::Status;
+use rocket::request::{self, FromRequest, Request};
+use rocket::response::{self, Responder, Response};
+
+use std::fs::File;
+use std::time::UNIX_EPOCH;
+
+/// Identifies one revision of a response through its `ETag` and `Last-Modified`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Validator {
+    etag: String,
+    last_modified: Option<DateTime<Utc>>,
+}
+
+impl Validator {
+    /// Makes a validator from a name that tells resources (and their revisions) apart
+    /// and the time the resource was last modified.
+    pub fn new<Tz: TimeZone>(name: &str, last_modified: Option<DateTime<Tz>>) -> Validator {
+        let last_modified = last_modified.map(|date| date.with_timezone(&Utc));
+        let version = last_modified
+            .map(|date| date.timestamp() * 1_000_000 + date.timestamp_subsec_micros() as i64)
+            .unwrap_or(0);
+        // Weak, since the body may be compressed differently for each client.
+        Validator {
+            etag: format!("W/\"{}-{:x}\"", name, version),
+            last_modified: last_modified,
+        }
+    }
+
+    pub fn for_file(file: &File) -> Result<Validator> {
+        let metadata = file.metadata()?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .chain_err(|| "file was modified before 1970")?;
+        let modified = Utc.timestamp(modified.as_secs() as i64, modified.subsec_nanos());
+        Ok(Validator::new(&format!("{:x}", metadata.len()), Some(modified)))
+    }
+}
+
+/// The conditional headers of a request.
+#[derive(Debug, Default)]
+pub struct Conditional {
+    if_none_match: Option<String>,
+    if_modified_since: Option<DateTime<Utc>>,
+}
+
+impl Conditional {
+    /// Whether the client already has the revision identified by `validator`.
+    /// `If-None-Match` takes precedence over `If-Modified-Since` as in RFC 7232.
+    pub fn is_fresh(&self, validator: &Validator) -> bool {
+        if let Some(ref tags) = self.if_none_match {
+            let etag = validator.etag.trim_left_matches("W/");
+            return tags.split(',')
+                .map(|tag| tag.trim())
+                .any(|tag| tag == "*" || tag.trim_left_matches("W/") == etag);
+        }
+        match (self.if_modified_since, validator.last_modified) {
+            (Some(since), Some(modified)) => modified.timestamp() <= since.timestamp(),
+            _ => false,
+        }
+    }
+
+    /// Calls `render` unless the client's copy is still fresh, in which case the
+    /// response becomes `304 Not Modified`.
+    pub fn respond<R, F>(
+        &self,
+        validator: Validator,
+        cache_control: &str,
+        render: F,
+    ) -> Result<Cached<R>>
+    where
+        F: FnOnce() -> Result<R>,
+    {
+        let body = if self.is_fresh(&validator) {
+            None
+        } else {
+            Some(render()?)
+        };
+        Ok(Cached {
+            validator: validator,
+            cache_control: cache_control.to_owned(),
+            body: body,
+        })
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for Conditional {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Conditional, ()> {
+        let headers = request.headers();
+        Outcome::Success(Conditional {
+            if_none_match: headers.get_one("If-None-Match").map(|value| value.to_owned()),
+            if_modified_since: headers
+                .get_one("If-Modified-Since")
+                .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+                .map(|date| date.with_timezone(&Utc)),
+        })
+    }
+}
+
+/// A response with `ETag`, `Last-Modified` and `Cache-Control` headers.
+pub struct Cached<R> {
+    validator: Validator,
+    cache_control: String,
+    body: Option<R>,
+}
+
+impl<'r, R: Responder<'r>> Responder<'r> for Cached<R> {
+    fn respond_to(self, request: &Request) -> response::Result<'r> {
+        let mut response = match self.body {
+            Some(body) => body.respond_to(request)?,
+            None => Response::build().status(Status::NotModified).finalize(),
+        };
+        response.set_raw_header("ETag", self.validator.etag);
+        if let Some(date) = self.validator.last_modified {
+            response.set_raw_header(
+                "Last-Modified",
+                date.format("%a, %d %b %Y %H:%M:%S GMT").to_string(),
+            );
+        }
+        response.set_raw_header("Cache-Control", self.cache_control);
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn freshness() {
+        let date = Utc.ymd(2017, 12, 1).and_hms_milli(3, 4, 5, 678);
+        let validator = Validator::new("article-1", Some(date));
+        assert_eq!(validator.etag, "W/\"article-1-55f3e9f1d8bb0\"");
+
+        let mut conditional = Conditional::default();
+        assert!(!conditional.is_fresh(&validator));
+        conditional.if_modified_since = Some(Utc.ymd(2017, 12, 1).and_hms(3, 4, 5));
+        assert!(conditional.is_fresh(&validator));
+        conditional.if_modified_since = Some(Utc.ymd(2017, 12, 1).and_hms(3, 4, 4));
+        assert!(!conditional.is_fresh(&validator));
+
+        conditional.if_none_match = Some("\"x\", \"article-1-55f3e9f1d8bb0\"".to_owned());
+        assert!(conditional.is_fresh(&validator));
+        conditional.if_modified_since = Some(Utc.ymd(2017, 12, 1).and_hms(3, 4, 5));
+        conditional.if_none_match = Some("W/\"article-1-0\"".to_owned());
+        assert!(!conditional.is_fresh(&validator));
+    }
+}
@@ -0,0 +1,334 @@
+//! Social preview images ("cards") referenced from the OpenGraph tags of articles.
+
+use super::{CurrentSite, Site};
+use super::caching::{Cached, Conditional, Validator};
+use db::Connection;
+use db::article::Article;
+use errors::*;
+
+use png::{self, HasParameters};
+use rocket::State;
+use rocket::http::ContentType;
+use rocket::response::content::Content;
+use rusttype::{point, Font, FontCollection, Scale};
+
+use std::cmp;
+use std::fs::{self, File};
+use std::io;
+use std::io::prelude::*;
+use std::path::PathBuf;
+
+pub const WIDTH: u32 = 1200;
+pub const HEIGHT: u32 = 630;
+
+const PADDING: f32 = 80.0;
+const TITLE_SIZE: f32 = 64.0;
+const TITLE_LINES: usize = 3;
+const INFO_SIZE: f32 = 32.0;
+
+const BACKGROUND: [u8; 3] = [0xff, 0xff, 0xff];
+const ACCENT: [u8; 3] = [0x33, 0x66, 0x99];
+const TEXT: [u8; 3] = [0x22, 0x22, 0x22];
+const MUTED: [u8; 3] = [0x77, 0x77, 0x77];
+
+/// Characters that must not begin a line (行頭禁則).
+const NO_LINE_START: &'static str =
+    "、。，．・：；？！ー―‐～…‥）］｝」』】〉》〕’”ぁぃぅぇぉっゃゅょゎァィゥェォッャュョヮヵヶ,.:;!?)]}%";
+/// Characters that must not end a line (行末禁則).
+const NO_LINE_END: &'static str = "（［｛「『【〈《〔‘“([{";
+
+/// Renders cards with the configured font and keeps them in the cache directory.
+pub struct Cards {
+    font: Font<'static>,
+    dir: PathBuf,
+}
+
+impl Cards {
+    /// Loads the font named by `og_font`. Returns `None` when it is not configured.
+    pub fn from_site(site: &Site) -> Result<Option<Cards>> {
+        let path = match site.og_font {
+            Some(ref path) => path,
+            None => return Ok(None),
+        };
+        let mut data = Vec::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_end(&mut data))
+            .chain_err(|| format!("failed to read font {}", path.display()))?;
+        let font = FontCollection::from_bytes(data)
+            .into_font()
+            .chain_err(|| format!("invalid font {}", path.display()))?;
+        Ok(Some(Cards {
+            font: font,
+            dir: site.cache_dir.join("og"),
+        }))
+    }
+
+    /// Returns the PNG image of an article, rendering it unless a cached image of
+    /// the same revision, and of the same revision of the settings, exists.
+    pub fn get(&self, article: &Article, site: &Site) -> Result<Vec<u8>> {
+        let name = format!(
+            "{}-{}-{}.png",
+            article.id,
+            article.updated_at.timestamp(),
+            settings_revision(site)
+        );
+        let path = self.dir.join(&name);
+        let mut data = Vec::new();
+        if File::open(&path)
+            .and_then(|mut file| file.read_to_end(&mut data))
+            .is_ok()
+        {
+            return Ok(data);
+        }
+
+        let data = self.render(article, site)?;
+        fs::create_dir_all(&self.dir)?;
+        let prefix = format!("{}-", article.id);
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            if entry.file_name().to_string_lossy().starts_with(&prefix) {
+                // A concurrent request may have removed it already.
+                match fs::remove_file(entry.path()) {
+                    Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+                    result => result?,
+                }
+            }
+        }
+        // Write to a temporary file first so that concurrent requests never read a
+        // partially written image.
+        let temp = self.dir.join(format!(".{}", name));
+        File::create(&temp)?.write_all(&data)?;
+        fs::rename(&temp, &path)?;
+        Ok(data)
+    }
+
+    fn render(&self, article: &Article, site: &Site) -> Result<Vec<u8>> {
+        let mut canvas = Canvas::new(WIDTH, HEIGHT, BACKGROUND);
+        canvas.fill(0, 0, 16, HEIGHT, ACCENT);
+
+        let width = WIDTH as f32 - PADDING * 2.0;
+        let site_name = &site.settings.site_name;
+        self.draw(&mut canvas, site_name, INFO_SIZE, PADDING, PADDING, MUTED);
+        let title_scale = Scale::uniform(TITLE_SIZE);
+        let lines = wrap(&article.title, TITLE_LINES, |text| {
+            self.measure(text, title_scale) <= width
+        });
+        let mut y = PADDING + INFO_SIZE * 2.5;
+        for line in lines {
+            self.draw(&mut canvas, &line, TITLE_SIZE, PADDING, y, TEXT);
+            y += TITLE_SIZE * 1.4;
+        }
+        let info = format!(
+            "{} · {}",
+            article.author.name,
+            article
+                .created_at
+                .with_timezone(&site.settings.timezone)
+                .format("%Y-%m-%d")
+        );
+        let bottom = HEIGHT as f32 - PADDING - INFO_SIZE;
+        self.draw(&mut canvas, &info, INFO_SIZE, PADDING, bottom, MUTED);
+
+        canvas.encode()
+    }
+
+    fn measure(&self, text: &str, scale: Scale) -> f32 {
+        self.font
+            .layout(text, scale, point(0.0, 0.0))
+            .last()
+            .map(|glyph| glyph.position().x + glyph.unpositioned().h_metrics().advance_width)
+            .unwrap_or(0.0)
+    }
+
+    /// Draws a line of text whose top left corner is at (`x`, `y`).
+    fn draw(&self, canvas: &mut Canvas, text: &str, size: f32, x: f32, y: f32, color: [u8; 3]) {
+        let scale = Scale::uniform(size);
+        let ascent = self.font.v_metrics(scale).ascent;
+        for glyph in self.font.layout(text, scale, point(x, y + ascent)) {
+            if let Some(bounds) = glyph.pixel_bounding_box() {
+                glyph.draw(|gx, gy, coverage| {
+                    canvas.blend(
+                        bounds.min.x + gx as i32,
+                        bounds.min.y + gy as i32,
+                        color,
+                        coverage,
+                    )
+                });
+            }
+        }
+    }
+}
+
+/// An RGB image buffer.
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32, color: [u8; 3]) -> Canvas {
+        let mut canvas = Canvas {
+            width: width,
+            height: height,
+            pixels: vec![0; (width * height * 3) as usize],
+        };
+        canvas.fill(0, 0, width, height, color);
+        canvas
+    }
+
+    fn fill(&mut self, x: u32, y: u32, width: u32, height: u32, color: [u8; 3]) {
+        for py in y..(y + height).min(self.height) {
+            for px in x..(x + width).min(self.width) {
+                let i = ((py * self.width + px) * 3) as usize;
+                self.pixels[i..i + 3].copy_from_slice(&color);
+            }
+        }
+    }
+
+    fn blend(&mut self, x: i32, y: i32, color: [u8; 3], alpha: f32) {
+        if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+            return;
+        }
+        let i = ((y as u32 * self.width + x as u32) * 3) as usize;
+        for (pixel, &c) in self.pixels[i..i + 3].iter_mut().zip(&color) {
+            *pixel = (*pixel as f32 * (1.0 - alpha) + c as f32 * alpha).round() as u8;
+        }
+    }
+
+    fn encode(&self) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut data, self.width, self.height);
+            encoder
+                .set(png::ColorType::RGB)
+                .set(png::BitDepth::Eight);
+            encoder
+                .write_header()
+                .and_then(|mut writer| writer.write_image_data(&self.pixels))
+                .chain_err(|| "failed to encode PNG")?;
+        }
+        Ok(data)
+    }
+}
+
+/// Splits text into units that must stay on one line: a Latin word, a single
+/// CJK character or a space, with characters that may not start or end a line
+/// attached to their neighbours.
+fn units(text: &str) -> Vec<String> {
+    let is_word = |c: char| !c.is_whitespace() && (c as u32) < 0x2e80;
+    let mut units: Vec<String> = Vec::new();
+    let mut glue = false;
+    for c in text.chars() {
+        let join = match units.last() {
+            Some(last) => {
+                let prev = last.chars().last().unwrap_or(' ');
+                glue || NO_LINE_START.contains(c) || (is_word(c) && is_word(prev))
+            }
+            None => false,
+        };
+        if join {
+            if let Some(last) = units.last_mut() {
+                last.push(c);
+            }
+        } else {
+            units.push(c.to_string());
+        }
+        glue = NO_LINE_END.contains(c);
+    }
+    units
+}
+
+/// Greedily wraps text into at most `max_lines` lines for which `fits` holds,
+/// ending the last line with an ellipsis when the text is cut.
+fn wrap<F: Fn(&str) -> bool>(text: &str, max_lines: usize, fits: F) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut cut = false;
+    for unit in units(text.trim()) {
+        let candidate = format!("{}{}", line, unit);
+        if line.is_empty() || fits(candidate.trim_right()) {
+            line = candidate;
+            continue;
+        }
+        lines.push(line.trim_right().to_owned());
+        if lines.len() == max_lines {
+            cut = true;
+            break;
+        }
+        line = unit.trim_left().to_owned();
+    }
+    if !cut && !line.trim().is_empty() {
+        lines.push(line.trim_right().to_owned());
+    }
+    if cut {
+        if let Some(last) = lines.last_mut() {
+            while !last.is_empty() && !fits(&format!("{}…", last)) {
+                last.pop();
+            }
+            let trimmed = last.trim_right().len();
+            last.truncate(trimmed);
+            last.push('…');
+        }
+    }
+    lines
+}
+
+/// Images show the site name, so they change along with the settings.
+fn settings_revision(site: &Site) -> i64 {
+    site.settings
+        .updated_at
+        .map(|updated_at| updated_at.timestamp())
+        .unwrap_or(0)
+}
+
+#[get("/article/<id>/og.png")]
+pub fn og_image(
+    conn: Connection,
+    site: CurrentSite,
+    cards: State<Option<Cards>>,
+    conditional: Conditional,
+    id: i32,
+) -> Result<Option<Cached<Content<Vec<u8>>>>> {
+    let cards = match *cards {
+        Some(ref cards) => cards,
+        None => return Ok(None),
+    };
+    let updated_at = match Article::revision(&*conn, id)? {
+        Some((updated_at, false)) => updated_at,
+        _ => return Ok(None),
+    };
+    let validator = Validator::new(
+        &format!("og-{}-{}", id, settings_revision(&site)),
+        cmp::max(Some(updated_at), site.settings.updated_at),
+    );
+    let response = conditional.respond(validator, &site.cache_control, || {
+        let article = Article::get(&*conn, id)?;
+        Ok(Content(ContentType::PNG, cards.get(&article, &site)?))
+    })?;
+    Ok(Some(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_japanese() {
+        let fits = |text: &str| text.chars().count() <= 4;
+        assert_eq!(
+            wrap("今日は「雑記」を書いた。", 4, &fits),
+            vec!["今日は", "「雑記」", "を書い", "た。"]
+        );
+        assert_eq!(wrap("今日は「雑記」を書いた。", 2, &fits), vec!["今日は", "「雑記…"]);
+        assert_eq!(wrap("ちょっとした話", 3, &fits), vec!["ちょっと", "した話"]);
+    }
+
+    #[test]
+    fn wrap_words() {
+        let fits = |text: &str| text.chars().count() <= 8;
+        assert_eq!(wrap("Rust で web アプリ", 3, &fits), vec!["Rust で", "web アプリ"]);
+        assert_eq!(wrap("Rust で web アプリを作る話", 2, &fits), vec!["Rust で", "web アプリ…"]);
+        assert_eq!(wrap("extraordinary", 3, &fits), vec!["extraordinary"]);
+    }
+}
@@ -0,0 +1,114 @@
+//! Line by line differences between two texts, shown when an article has been saved
+//! by someone else while it was being edited.
+
+use std::cmp;
+
+/// Most entries of the table compared lines are matched with, which takes memory
+/// and time in proportion. Larger changes are shown as all removed and added.
+const MAX_TABLE_SIZE: usize = 1_000_000;
+
+#[derive(Debug, PartialEq)]
+pub enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Lists the lines of `old` and `new` in order, marking the ones only in `old` as
+/// removed and the ones only in `new` as added, so that as few lines as possible
+/// are marked. When the changed parts are too long to match, all of their lines
+/// are marked instead.
+pub fn lines<'a>(old: &'a str, new: &'a str) -> Vec<Line<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    // Only the part between the common head and tail needs comparing, which is
+    // usually small.
+    let head = old.iter().zip(&new).take_while(|&(a, b)| a == b).count();
+    let tail = old[head..]
+        .iter()
+        .rev()
+        .zip(new[head..].iter().rev())
+        .take_while(|&(a, b)| a == b)
+        .count();
+    let a = &old[head..old.len() - tail];
+    let b = &new[head..new.len() - tail];
+    let mut result: Vec<Line> = old[..head].iter().map(|&line| Line::Same(line)).collect();
+    if (a.len() + 1).saturating_mul(b.len() + 1) > MAX_TABLE_SIZE {
+        result.extend(a.iter().map(|&line| Line::Removed(line)));
+        result.extend(b.iter().map(|&line| Line::Added(line)));
+        result.extend(old[old.len() - tail..].iter().map(|&line| Line::Same(line)));
+        return result;
+    }
+
+    // common[i][j] is the length of the longest common subsequence of a[i..] and
+    // b[j..].
+    let mut common = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            common[i][j] = if a[i] == b[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                cmp::max(common[i + 1][j], common[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            result.push(Line::Same(a[i]));
+            i += 1;
+            j += 1;
+        } else if common[i + 1][j] >= common[i][j + 1] {
+            result.push(Line::Removed(a[i]));
+            i += 1;
+        } else {
+            result.push(Line::Added(b[j]));
+            j += 1;
+        }
+    }
+    result.extend(a[i..].iter().map(|&line| Line::Removed(line)));
+    result.extend(b[j..].iter().map(|&line| Line::Added(line)));
+    result.extend(old[old.len() - tail..].iter().map(|&line| Line::Same(line)));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::Line::*;
+
+    #[test]
+    fn diff_lines() {
+        assert_eq!(
+            lines("a\nb\nc\nd\ne", "a\nc\nx\nd\ne\nf"),
+            vec![
+                Same("a"),
+                Removed("b"),
+                Same("c"),
+                Added("x"),
+                Same("d"),
+                Same("e"),
+                Added("f"),
+            ]
+        );
+        assert_eq!(lines("a\nb", "a\nb"), vec![Same("a"), Same("b")]);
+        assert_eq!(lines("", "a"), vec![Added("a")]);
+        assert_eq!(lines("a\na", "a"), vec![Same("a"), Removed("a")]);
+    }
+
+    #[test]
+    fn too_long_to_match() {
+        let text = |prefix| {
+            let lines: Vec<String> = (0..2000).map(|i| format!("{}{}", prefix, i)).collect();
+            format!("head\n{}\ntail", lines.join("\n"))
+        };
+        let (old, new) = (text("a"), text("b"));
+        let result = lines(&old, &new);
+        assert_eq!(result.len(), 4002);
+        assert_eq!(result[0], Same("head"));
+        assert_eq!(result[1], Removed("a0"));
+        assert_eq!(result[2001], Added("b0"));
+        assert_eq!(result[4001], Same("tail"));
+    }
+}
@@ -0,0 +1,635 @@
+use errors::*;
+
+use ammonia::Builder;
+use maud::{Escaper, Render};
+use pulldown_cmark::{html, Event, Options, Parser, Tag, OPTION_ENABLE_FOOTNOTES,
+                     OPTION_ENABLE_TABLES};
+use rocket::config::{Config, ConfigError};
+use syntect::parsing::{BasicScopeStackOp, ParseState, ScopeStack, ScopeStackOp, SyntaxSet};
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+/// Stands in for a highlighted code block until the document is sanitized, as the
+/// sanitizer would strip the classes of the highlighted markup.
+const HIGHLIGHTED: &'static str = "<pre class=\"hl-code\"><code>\u{fffc}</code></pre>";
+
+/// Stand in for the checkboxes of task list items, so that the sanitizer can strip
+/// every `input` element written in the source.
+const CHECKBOX: char = '\u{fff9}';
+const CHECKED_CHECKBOX: char = '\u{fffa}';
+
+/// Version of the HTML rendered from Markdown, to be raised whenever a change to
+/// the rendering makes the same source render differently.
+const RENDERER_VERSION: u32 = 2;
+
+thread_local! {
+    /// Syntax definitions can not be shared between threads, so every thread loads
+    /// its own.
+    static SYNTAXES: SyntaxSet = SyntaxSet::load_defaults_newlines();
+}
+
+lazy_static! {
+    /// Sanitizer policy: ammonia's defaults plus the markup produced by the enabled
+    /// Markdown extensions.
+    static ref SANITIZER: Builder<'static> = {
+        let mut builder = Builder::default();
+        builder
+            .add_tag_attributes("th", ["align"].iter().cloned())
+            .add_tag_attributes("td", ["align"].iter().cloned())
+            .add_tag_attributes("div", ["id"].iter().cloned());
+        for tag in &["h1", "h2", "h3", "h4", "h5", "h6"] {
+            builder.add_tag_attributes(tag, ["id"].iter().cloned());
+        }
+        builder
+            .add_allowed_classes(
+                "sup",
+                ["footnote-reference", "footnote-definition-label"].iter().cloned(),
+            )
+            .add_allowed_classes("div", ["footnote-definition"].iter().cloned())
+            .add_allowed_classes("nav", ["toc"].iter().cloned())
+            .add_allowed_classes("pre", ["hl-code"].iter().cloned());
+        builder
+    };
+}
+
+/// Markdown syntax extensions enabled for the site, configured with the
+/// `markdown_extensions` array in Rocket.toml. All of them are enabled by default.
+///
+/// Tables and footnotes are handled by the parser itself, while strikethrough and
+/// task lists are applied to its events afterwards.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Extensions {
+    options: Options,
+    strikethrough: bool,
+    tasklists: bool,
+}
+
+impl Extensions {
+    pub fn from_names(names: &[&str]) -> Result<Extensions> {
+        let mut extensions = Extensions {
+            options: Options::empty(),
+            strikethrough: false,
+            tasklists: false,
+        };
+        for name in names {
+            match *name {
+                "tables" => extensions.options.insert(OPTION_ENABLE_TABLES),
+                "footnotes" => extensions.options.insert(OPTION_ENABLE_FOOTNOTES),
+                "strikethrough" => extensions.strikethrough = true,
+                "tasklists" => extensions.tasklists = true,
+                _ => bail!("unknown markdown extension: {}", name),
+            }
+        }
+        Ok(extensions)
+    }
+
+    /// Identifies the HTML rendered with these extensions by this version of the
+    /// renderer, e.g. for validators of pages showing rendered articles.
+    pub fn fingerprint(&self) -> String {
+        let flags = [
+            (self.options.contains(OPTION_ENABLE_TABLES), 't'),
+            (self.options.contains(OPTION_ENABLE_FOOTNOTES), 'f'),
+            (self.strikethrough, 's'),
+            (self.tasklists, 'l'),
+        ];
+        let enabled: String = flags.iter().filter(|&&(on, _)| on).map(|&(_, c)| c).collect();
+        format!("md{}{}", RENDERER_VERSION, enabled)
+    }
+
+    /// Parses a Markdown document into events with the extensions applied.
+    fn parse<'a>(&self, source: &'a str) -> Vec<Event<'a>> {
+        let mut events = Parser::new_ext(source, self.options).collect();
+        if self.strikethrough {
+            events = strikethrough(events);
+        }
+        if self.tasklists {
+            events = task_lists(events);
+        }
+        events
+    }
+
+    pub fn from_config(config: &Config) -> Result<Extensions> {
+        match config.get_slice("markdown_extensions") {
+            Ok(values) => {
+                let names = values
+                    .iter()
+                    .map(|value| value.as_str())
+                    .collect::<Option<Vec<_>>>()
+                    .chain_err(|| "markdown_extensions must be an array of strings")?;
+                Extensions::from_names(&names)
+            }
+            Err(ConfigError::NotFound) => Ok(Extensions::default()),
+            Err(_) => bail!("markdown_extensions must be an array of strings"),
+        }
+    }
+}
+
+impl Default for Extensions {
+    fn default() -> Extensions {
+        Extensions {
+            options: OPTION_ENABLE_TABLES | OPTION_ENABLE_FOOTNOTES,
+            strikethrough: true,
+            tasklists: true,
+        }
+    }
+}
+
+pub struct Markdown<'a>(pub &'a str, pub Extensions);
+
+/// Turns text between pairs of `~~` into `<del>` elements. A pair has to be in
+/// the same run of text, and code is left as it is.
+fn strikethrough(events: Vec<Event>) -> Vec<Event> {
+    let mut result = Vec::with_capacity(events.len());
+    let mut in_code = 0;
+    for event in events {
+        match event {
+            Event::Start(Tag::Code) | Event::Start(Tag::CodeBlock(_)) => in_code += 1,
+            Event::End(Tag::Code) | Event::End(Tag::CodeBlock(_)) => in_code -= 1,
+            Event::Text(ref text) if in_code == 0 && text.matches("~~").count() >= 2 => {
+                let parts = text.split("~~").collect::<Vec<_>>();
+                // An unpaired `~~` at the end stays as it is.
+                let paired = if parts.len() % 2 == 0 {
+                    parts.len() - 2
+                } else {
+                    parts.len() - 1
+                };
+                for (i, part) in parts.iter().enumerate() {
+                    if i > 0 && i <= paired {
+                        let tag = if i % 2 == 1 { "<del>" } else { "</del>" };
+                        result.push(Event::InlineHtml(tag.into()));
+                    } else if i > paired {
+                        result.push(Event::Text("~~".into()));
+                    }
+                    if !part.is_empty() {
+                        result.push(Event::Text(part.to_string().into()));
+                    }
+                }
+                continue;
+            }
+            _ => {}
+        }
+        result.push(event);
+    }
+    result
+}
+
+/// Turns list items beginning with `[ ]` or `[x]` into disabled checkboxes.
+fn task_lists(events: Vec<Event>) -> Vec<Event> {
+    let mut result = Vec::with_capacity(events.len());
+    let mut item_start = false;
+    let mut pending: Vec<Event> = Vec::new();
+    for event in events {
+        if !item_start {
+            item_start = match event {
+                Event::Start(Tag::Item) => true,
+                _ => false,
+            };
+            result.push(event);
+            continue;
+        }
+        match event {
+            // A loose list wraps the item in a paragraph.
+            Event::Start(Tag::Paragraph) if pending.is_empty() => {
+                result.push(event);
+                continue;
+            }
+            // The parser may split `[x]` into several runs of text.
+            Event::Text(_) => {
+                pending.push(event);
+                continue;
+            }
+            _ => {}
+        }
+        item_start = match event {
+            Event::Start(Tag::Item) => true,
+            _ => false,
+        };
+        result.extend(task_list_marker(pending.drain(..).collect()));
+        result.push(event);
+    }
+    result.extend(task_list_marker(pending));
+    result
+}
+
+fn task_list_marker(texts: Vec<Event>) -> Vec<Event> {
+    let text = texts
+        .iter()
+        .filter_map(|event| match *event {
+            Event::Text(ref text) => Some(text.as_ref()),
+            _ => None,
+        })
+        .collect::<String>();
+    let checked = if text.starts_with("[ ] ") {
+        false
+    } else if text.starts_with("[x] ") || text.starts_with("[X] ") {
+        true
+    } else {
+        return texts;
+    };
+    let checkbox = if checked { CHECKED_CHECKBOX } else { CHECKBOX };
+    vec![
+        Event::InlineHtml(checkbox.to_string().into()),
+        Event::Text(text[3..].to_owned().into()),
+    ]
+}
+
+/// Replaces fenced code blocks in a known language with placeholders and returns
+/// them along with their highlighted HTML. Other code blocks are left to the plain
+/// HTML renderer.
+fn highlight_code_blocks<'a, I: Iterator<Item = Event<'a>>>(
+    events: I,
+) -> (Vec<Event<'a>>, Vec<String>) {
+    let mut result = Vec::new();
+    let mut highlighted = Vec::new();
+    let mut block: Option<Vec<Event<'a>>> = None;
+    for event in events {
+        let fenced = match event {
+            Event::Start(Tag::CodeBlock(ref info)) => !info.is_empty(),
+            _ => false,
+        };
+        if fenced {
+            block = Some(vec![event]);
+            continue;
+        }
+        match event {
+            Event::End(Tag::CodeBlock(_)) if block.is_some() => {
+                let mut events = block.take().unwrap_or_default();
+                events.push(event);
+                match highlight_block(&events) {
+                    Some(html) => {
+                        result.push(Event::Html(format!("{}\n", HIGHLIGHTED).into()));
+                        highlighted.push(html);
+                    }
+                    None => result.extend(events),
+                }
+            }
+            _ => match block {
+                Some(ref mut events) => events.push(event),
+                None => result.push(event),
+            },
+        }
+    }
+    (result, highlighted)
+}
+
+fn highlight_block(events: &[Event]) -> Option<String> {
+    let lang = match events.first() {
+        Some(&Event::Start(Tag::CodeBlock(ref info))) => {
+            info.split(' ').next().unwrap_or("")
+        }
+        _ => return None,
+    };
+    let code = events
+        .iter()
+        .filter_map(|event| match *event {
+            Event::Text(ref text) => Some(text.as_ref()),
+            _ => None,
+        })
+        .collect::<String>();
+    SYNTAXES.with(|syntaxes| {
+        let syntax = syntaxes.find_syntax_by_token(lang)?;
+        let mut state = ParseState::new(syntax);
+        let mut stack = ScopeStack::new();
+        let mut html = String::from("<pre class=\"hl-code\"><code>");
+        let mut rest = code.as_str();
+        while !rest.is_empty() {
+            let end = rest.find('\n').map(|i| i + 1).unwrap_or(rest.len());
+            let (line, next) = rest.split_at(end);
+            let ops = state.parse_line(line);
+            push_classed_line(&mut html, line, &ops, &mut stack);
+            rest = next;
+        }
+        for _ in 0..stack.len() {
+            html.push_str("</span>");
+        }
+        html.push_str("</code></pre>");
+        Some(html)
+    })
+}
+
+/// Marks up a line with a `span` for every scope, whose classes are the atoms of
+/// the scope name prefixed with `hl-` (e.g. `hl-source hl-rust`) as styled by
+/// `static/css/highlight.css`. Scopes stay open across lines in `stack`.
+fn push_classed_line(
+    html: &mut String,
+    line: &str,
+    ops: &[(usize, ScopeStackOp)],
+    stack: &mut ScopeStack,
+) {
+    let mut start = 0;
+    for &(i, ref op) in ops {
+        if i > start {
+            let _ = Escaper::new(html).write_str(&line[start..i]);
+            start = i;
+        }
+        stack.apply_with_hook(op, |op, _| match op {
+            BasicScopeStackOp::Push(scope) => {
+                html.push_str("<span class=\"");
+                let name = scope.build_string();
+                for (j, atom) in name.split('.').enumerate() {
+                    if j > 0 {
+                        html.push(' ');
+                    }
+                    html.push_str("hl-");
+                    html.push_str(atom);
+                }
+                html.push_str("\">");
+            }
+            BasicScopeStackOp::Pop => html.push_str("</span>"),
+        });
+    }
+    let _ = Escaper::new(html).write_str(&line[start..]);
+}
+
+/// Makes a heading anchor from its text. Letters of any script are kept so that
+/// Japanese headings get readable ids, and other characters are dropped.
+fn anchor(text: &str) -> String {
+    let mut id = String::new();
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            id.extend(c.to_lowercase());
+        } else if (c.is_whitespace() || c == '-' || c == '_') && !id.is_empty()
+            && !id.ends_with('-')
+        {
+            id.push('-');
+        }
+    }
+    let id = id.trim_right_matches('-');
+    if id.is_empty() {
+        "section".to_owned()
+    } else {
+        id.to_owned()
+    }
+}
+
+struct Heading {
+    level: i32,
+    id: String,
+    text: String,
+}
+
+/// Gives every heading an `id` anchor, numbering duplicates, and replaces a
+/// paragraph consisting only of `[TOC]` with a table of contents.
+fn add_heading_anchors(events: Vec<Event>) -> Vec<Event> {
+    let mut headings = Vec::new();
+    let mut counts = HashMap::new();
+    let mut used = HashSet::new();
+    let mut text: Option<String> = None;
+    for event in &events {
+        match *event {
+            Event::Start(Tag::Header(_)) => text = Some(String::new()),
+            Event::Text(ref s) => {
+                if let Some(ref mut text) = text {
+                    text.push_str(s);
+                }
+            }
+            Event::End(Tag::Header(level)) => {
+                let text = text.take().unwrap_or_default();
+                let base = anchor(&text);
+                // A numbered id may be taken by a heading whose text ends with the
+                // number, so numbers are tried until one is free.
+                let count = counts.entry(base.clone()).or_insert(0);
+                let id = loop {
+                    let candidate = if *count == 0 {
+                        base.clone()
+                    } else {
+                        format!("{}-{}", base, count)
+                    };
+                    *count += 1;
+                    if used.insert(candidate.clone()) {
+                        break candidate;
+                    }
+                };
+                headings.push(Heading {
+                    level: level,
+                    id: id,
+                    text: text,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    let mut result = Vec::with_capacity(events.len());
+    let mut ids = headings.iter().map(|heading| &heading.id);
+    let mut paragraph: Option<Vec<Event>> = None;
+    for event in events {
+        match event {
+            Event::Start(Tag::Header(level)) => {
+                let id = ids.next().map(|id| id.as_str()).unwrap_or("");
+                result.push(Event::Html(format!("<h{} id=\"{}\">", level, id).into()));
+            }
+            Event::End(Tag::Header(level)) => {
+                result.push(Event::Html(format!("</h{}>\n", level).into()));
+            }
+            Event::Start(Tag::Paragraph) => paragraph = Some(vec![event]),
+            Event::End(Tag::Paragraph) if paragraph.is_some() => {
+                let mut events = paragraph.take().unwrap_or_default();
+                let is_toc = events[1..].iter().all(|event| match *event {
+                    Event::Text(_) => true,
+                    _ => false,
+                }) && events[1..]
+                    .iter()
+                    .filter_map(|event| match *event {
+                        Event::Text(ref s) => Some(s.as_ref()),
+                        _ => None,
+                    })
+                    .collect::<String>()
+                    .trim() == "[TOC]";
+                if is_toc {
+                    table_of_contents(&headings, &mut result);
+                } else {
+                    events.push(event);
+                    result.extend(events);
+                }
+            }
+            _ => match paragraph {
+                Some(ref mut events) => events.push(event),
+                None => result.push(event),
+            },
+        }
+    }
+    result
+}
+
+fn table_of_contents<'a>(headings: &[Heading], result: &mut Vec<Event<'a>>) {
+    if headings.is_empty() {
+        return;
+    }
+    result.push(Event::Html("<nav class=\"toc\">\n".into()));
+    let mut levels: Vec<i32> = Vec::new();
+    for heading in headings {
+        if levels.last().map(|&level| heading.level > level).unwrap_or(true) {
+            result.push(Event::Start(Tag::List(None)));
+            levels.push(heading.level);
+        } else {
+            result.push(Event::End(Tag::Item));
+            while levels.len() > 1 && heading.level < levels[levels.len() - 1] {
+                result.push(Event::End(Tag::List(None)));
+                result.push(Event::End(Tag::Item));
+                levels.pop();
+            }
+        }
+        let href = format!("#{}", heading.id);
+        result.push(Event::Start(Tag::Item));
+        result.push(Event::Start(Tag::Link(href.clone().into(), "".into())));
+        result.push(Event::Text(heading.text.clone().into()));
+        result.push(Event::End(Tag::Link(href.into(), "".into())));
+    }
+    for _ in levels {
+        result.push(Event::End(Tag::Item));
+        result.push(Event::End(Tag::List(None)));
+    }
+    result.push(Event::Html("</nav>\n".into()));
+}
+
+/// Extracts the text of a Markdown document for excerpts and descriptions, leaving
+/// out markup, code blocks, footnotes and raw HTML.
+pub fn plain_text(source: &str, extensions: Extensions) -> String {
+    let mut text = String::new();
+    let mut skipped = 0;
+    for event in extensions.parse(source) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) | Event::Start(Tag::FootnoteDefinition(_)) => {
+                skipped += 1
+            }
+            Event::End(Tag::CodeBlock(_)) | Event::End(Tag::FootnoteDefinition(_)) => skipped -= 1,
+            Event::Text(ref s) if skipped == 0 => text.push_str(s),
+            Event::End(Tag::Emphasis) | Event::End(Tag::Strong) | Event::End(Tag::Code)
+            | Event::End(Tag::Link(..)) => {}
+            Event::SoftBreak | Event::HardBreak | Event::End(_) => text.push(' '),
+            _ => {}
+        }
+    }
+    text.replace("[TOC]", "")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Returns the URL of the first image in a Markdown document.
+pub fn first_image(source: &str, extensions: Extensions) -> Option<String> {
+    Parser::new_ext(source, extensions.options)
+        .filter_map(|event| match event {
+            Event::Start(Tag::Image(url, _)) => Some(url.to_string()),
+            _ => None,
+        })
+        .next()
+}
+
+impl<'a> Render for Markdown<'a> {
+    fn render_to(&self, w: &mut String) {
+        let source = self.0
+            .replace(|c| c == '\u{fffc}' || c == CHECKBOX || c == CHECKED_CHECKBOX, "");
+        let (events, highlighted) = highlight_code_blocks(self.1.parse(&source).into_iter());
+        let events = add_heading_anchors(events);
+        let mut html = String::new();
+        html::push_html(&mut html, events.into_iter());
+        let mut safe_html = SANITIZER
+            .clean(&html)
+            .to_string()
+            .replace(CHECKBOX, "<input disabled=\"\" type=\"checkbox\">")
+            .replace(CHECKED_CHECKBOX, "<input disabled=\"\" type=\"checkbox\" checked=\"\">");
+        for block in highlighted {
+            safe_html = safe_html.replacen(HIGHLIGHTED, &block, 1);
+        }
+        w.push_str(&safe_html);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(source: &str, extensions: Extensions) -> String {
+        let mut html = String::new();
+        Markdown(source, extensions).render_to(&mut html);
+        html
+    }
+
+    #[test]
+    fn extensions() {
+        let html = render("a | b\n--|--\n1 | 2\n", Extensions::default());
+        assert!(html.contains("<table>"));
+        let html = render("~~old~~", Extensions::default());
+        assert!(html.contains("<del>old</del>"));
+        let html = render("- [x] done\n", Extensions::default());
+        assert!(html.contains("<input disabled=\"\" type=\"checkbox\" checked=\"\">"));
+        let html = render("- [ ] todo\n", Extensions::default());
+        assert!(html.contains("<input disabled=\"\" type=\"checkbox\"> todo"));
+        let html = render("<input type=\"text\" name=\"q\"> \u{fff9}", Extensions::default());
+        assert_eq!(html, "<p> </p>\n");
+        let html = render("note[^1]\n\n[^1]: text\n", Extensions::default());
+        assert!(html.contains("<sup class=\"footnote-reference\"><a href=\"#1\""));
+        assert!(html.contains("<div class=\"footnote-definition\" id=\"1\">"));
+    }
+
+    #[test]
+    fn highlight() {
+        let html = render("```rust\nfn main() {}\n```\n", Extensions::default());
+        assert!(html.starts_with("<pre class=\"hl-code\"><code><span class=\"hl-source hl-rust\">"));
+        let html = render("```unknown\n<b>\n```\n", Extensions::default());
+        assert_eq!(html, "<pre><code>&lt;b&gt;\n</code></pre>\n");
+        let html = render("<span class=\"evil hl-x\">a</span>", Extensions::default());
+        assert_eq!(html, "<p><span>a</span></p>\n");
+        let html = render("<pre class=\"hl-code\"><code>\u{fffc}</code></pre>", Extensions::default());
+        assert_eq!(html, "<pre class=\"hl-code\"><code></code></pre>");
+    }
+
+    #[test]
+    fn heading_anchors() {
+        let html = render("# Hello, World!\n\n## はじめに\n\n## Hello World\n", Extensions::default());
+        assert_eq!(
+            html,
+            "<h1 id=\"hello-world\">Hello, World!</h1>\n\
+             <h2 id=\"はじめに\">はじめに</h2>\n\
+             <h2 id=\"hello-world-1\">Hello World</h2>\n"
+        );
+        let html = render("## Foo\n\n## Foo\n\n## Foo-1\n", Extensions::default());
+        assert_eq!(
+            html,
+            "<h2 id=\"foo\">Foo</h2>\n\
+             <h2 id=\"foo-1\">Foo</h2>\n\
+             <h2 id=\"foo-1-1\">Foo-1</h2>\n"
+        );
+    }
+
+    #[test]
+    fn table_of_contents() {
+        let html = render("[TOC]\n\n# A\n\n## B\n\n# C\n", Extensions::default());
+        assert!(html.starts_with(
+            "<nav class=\"toc\">\n<ul>\n<li><a href=\"#a\" rel=\"noopener noreferrer\">A</a>\n\
+             <ul>\n<li><a href=\"#b\" rel=\"noopener noreferrer\">B</a></li>\n</ul>\n</li>\n\
+             <li><a href=\"#c\" rel=\"noopener noreferrer\">C</a></li>\n</ul>\n</nav>\n"
+        ));
+        let html = render("[TOC] is a marker\n", Extensions::default());
+        assert_eq!(html, "<p>[TOC] is a marker</p>\n");
+    }
+
+    #[test]
+    fn plain_text_test() {
+        let source = "[TOC]\n\n# Title\n\nSome *text*\nwith `code`.[^1]\n\n\
+                      ```\nfn main() {}\n```\n\n[^1]: note\n";
+        assert_eq!(
+            plain_text(source, Extensions::default()),
+            "Title Some text with code."
+        );
+    }
+
+    #[test]
+    fn first_image_test() {
+        let source = "text\n\n![a](/a.png)\n\n![b](/b.png)\n";
+        assert_eq!(first_image(source, Extensions::default()), Some("/a.png".to_owned()));
+        assert_eq!(first_image("text", Extensions::default()), None);
+    }
+
+    #[test]
+    fn disabled_extensions() {
+        let extensions = Extensions::from_names(&["tables"]).unwrap();
+        assert_eq!(render("~~old~~", extensions), "<p>~~old~~</p>\n");
+        assert!(Extensions::from_names(&["emoji"]).is_err());
+        assert_eq!(extensions.fingerprint(), "md2t");
+        assert_eq!(Extensions::default().fingerprint(), "md2tfsl");
+    }
+}
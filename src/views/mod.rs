@@ -1,138 +1,657 @@
 pub mod admin;
+pub mod assets;
+pub mod caching;
+pub mod card;
+pub mod diff;
+pub mod markdown;
+pub mod site;
+pub mod summary;
+pub mod theme;
+pub mod validation;
+
+pub use self::markdown::{Extensions, Markdown};
+pub use self::site::{CurrentSite, SharedSite, Site};
+pub use self::theme::{DefaultTheme, Theme};
+use self::caching::{Cached, Conditional, Validator};
+use self::summary::Summary;
 
 use db::Connection;
-use db::article::Article;
+use db::article::{Article, ArticleLink, Navigation, Rendered};
+use db::series::{Series, SeriesParts};
 use errors::*;
+use locale::Messages;
 
-use ammonia::clean;
-use maud::{html, Markup, Render, DOCTYPE};
-use pulldown_cmark::{html, Parser};
+use chrono::{DateTime, Local};
+use maud::{html, Markup, PreEscaped, Render, DOCTYPE};
+use postgres::GenericConnection;
 use rocket::request::Request;
 use rocket::response::NamedFile;
-use std::path::{Path, PathBuf};
+use std::borrow::Cow;
+use std::cmp;
+use std::path::PathBuf;
 
-pub const APP_NAME: &'static str = "雑記";
+/// Number of related articles listed under an article.
+pub const RELATED_ARTICLES: i64 = 5;
 
-struct Header<'a>(&'a str);
+/// Number of featured articles listed on the front page.
+pub const FEATURED_ARTICLES: i64 = 5;
 
-impl<'a> Render for Header<'a> {
+/// Metadata of a page rendered into its `<head>`. Everything but the title is
+/// optional; OpenGraph and Twitter Card tags are emitted for pages with a URL.
+#[derive(Default)]
+pub struct PageMeta<'a> {
+    pub title: &'a str,
+    pub description: Option<&'a str>,
+    pub url: Option<String>,
+    pub image: Option<String>,
+    pub published_time: Option<DateTime<Local>>,
+    pub modified_time: Option<DateTime<Local>>,
+    pub author: Option<&'a str>,
+}
+
+impl<'a> PageMeta<'a> {
+    pub fn titled(title: &'a str) -> PageMeta<'a> {
+        PageMeta {
+            title: title,
+            ..PageMeta::default()
+        }
+    }
+
+    pub fn for_article(article: &'a Article, description: &'a str, site: &Site) -> PageMeta<'a> {
+        // Prefer an image from the article itself over the generated card.
+        let image = markdown::first_image(&article.body, site.extensions)
+            .and_then(|image| site.url(&image))
+            .or_else(|| match site.og_font {
+                Some(_) => site.url(&format!("/article/{}/og.png", article.id)),
+                None => None,
+            });
+        PageMeta {
+            title: &article.title,
+            description: Some(description),
+            url: site.url(&format!("/article/{}", article.id)),
+            image: image,
+            published_time: Some(article.created_at),
+            modified_time: Some(article.updated_at),
+            author: Some(&article.author.name),
+        }
+    }
+
+    /// The content of `<title>`: the page title followed by the site name.
+    pub fn document_title(&self, site_name: &str) -> String {
+        if self.title.is_empty() {
+            site_name.to_owned()
+        } else {
+            format!("{} - {}", self.title, site_name)
+        }
+    }
+}
+
+/// The tags of `<head>` describing a page, shared by all themes.
+struct Head<'a>(&'a Site, &'a PageMeta<'a>);
+
+impl<'a> Render for Head<'a> {
     fn render(&self) -> Markup {
-        let title = if self.0.is_empty() {
-            APP_NAME.into()
+        let site_name = &self.0.settings.site_name;
+        let meta = self.1;
+        let title = meta.document_title(site_name);
+        let og_title = if meta.title.is_empty() {
+            site_name
+        } else {
+            meta.title
+        };
+        let card = if meta.image.is_some() {
+            "summary_large_image"
         } else {
-            format!("{} - {}", self.0, APP_NAME)
+            "summary"
         };
         html! {
-            (DOCTYPE)
             meta charset="utf-8";
             meta content="width=device-width" name="viewport";
+            @if let Some(description) = meta.description {
+                meta content=(description) name="description";
+            }
             title (title)
-            link href="/css/style.css" rel="stylesheet";
+            @if let Some(ref url) = meta.url {
+                link href=(url) rel="canonical";
+                meta content=(site_name) property="og:site_name";
+                meta content=(og_title) property="og:title";
+                meta content=(url) property="og:url";
+                @if meta.published_time.is_some() {
+                    meta content="article" property="og:type";
+                } @else {
+                    meta content="website" property="og:type";
+                }
+                @if let Some(description) = meta.description {
+                    meta content=(description) property="og:description";
+                }
+                @if let Some(ref image) = meta.image {
+                    meta content=(image) property="og:image";
+                }
+                @if let Some(time) = meta.published_time {
+                    meta content=(time.to_rfc3339()) property="article:published_time";
+                }
+                @if let Some(time) = meta.modified_time {
+                    meta content=(time.to_rfc3339()) property="article:modified_time";
+                }
+                @if let Some(author) = meta.author {
+                    meta content=(author) property="article:author";
+                }
+                meta content=(card) name="twitter:card";
+                meta content=(og_title) name="twitter:title";
+                @if let Some(description) = meta.description {
+                    meta content=(description) name="twitter:description";
+                }
+                @if let Some(ref image) = meta.image {
+                    meta content=(image) name="twitter:image";
+                }
+            }
+        }
+    }
+}
+
+struct Header<'a>(&'a Site, &'a PageMeta<'a>);
+
+impl<'a> Render for Header<'a> {
+    fn render(&self) -> Markup {
+        let site = self.0;
+        html! {
+            (DOCTYPE)
+            (Head(site, self.1))
+            link href=(site.assets.url("/css/style.css")) rel="stylesheet";
+            link href=(site.assets.url("/css/highlight.css")) rel="stylesheet";
             header {
                 h1 {
-                    a href="/" (APP_NAME)
+                    a href="/" (site.settings.site_name)
                 }
             }
         }
     }
 }
 
-pub struct Markdown<'a>(&'a str);
+struct Footer<'a>(&'a Site);
 
-impl<'a> Render for Markdown<'a> {
-    fn render_to(&self, w: &mut String) {
-        let parser = Parser::new(self.0);
-        let mut html = String::new();
-        html::push_html(&mut html, parser);
-        let safe_html = clean(&html);
-        w.push_str(&safe_html);
+impl<'a> Render for Footer<'a> {
+    fn render(&self) -> Markup {
+        let footer = &self.0.settings.footer;
+        html! {
+            @if !footer.is_empty() {
+                footer.site (footer)
+            }
+        }
     }
 }
 
-pub struct ArticleView<'a>(&'a Article);
+struct ArticleHeading<'a>(&'a Article, &'a Site);
+
+impl<'a> Render for ArticleHeading<'a> {
+    fn render(&self) -> Markup {
+        let article = self.0;
+        let settings = &self.1.settings;
+        let t = settings.locale.messages();
+        let created_at = article.created_at.with_timezone(&settings.timezone);
+        html! {
+            h1 {
+                a href={ "/article/" (article.id) } (article.title)
+            }
+            footer {
+                span {
+                    (t.posted_on)
+                    time datetime={ (created_at.format("%F")) } {
+                        (created_at.format(t.date_format))
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Renders the Markdown of an article into the HTML cached in the database.
+pub fn render_markdown(article: &Article, extensions: Extensions) -> Rendered {
+    let summary = match summary::summary(article, extensions) {
+        Summary::Markdown(summary) => Some(Markdown(summary, extensions).render().into_string()),
+        Summary::Excerpt(excerpt) => Some(html!((excerpt)).into_string()),
+        Summary::Full => None,
+    };
+    Rendered {
+        body: Markdown(&article.body, extensions).render().into_string(),
+        summary: summary,
+    }
+}
+
+/// Renders and stores the HTML of an article unless it is cached already.
+pub fn prepare(
+    conn: &GenericConnection,
+    article: &mut Article,
+    extensions: Extensions,
+) -> Result<()> {
+    if article.rendered.is_none() {
+        let rendered = render_markdown(article, extensions);
+        Article::store_rendered(conn, article.id, &article.updated_at, &rendered)?;
+        article.rendered = Some(rendered);
+    }
+    Ok(())
+}
+
+fn rendered(article: &Article, extensions: Extensions) -> Cow<Rendered> {
+    match article.rendered {
+        Some(ref rendered) => Cow::Borrowed(rendered),
+        None => Cow::Owned(render_markdown(article, extensions)),
+    }
+}
+
+pub struct ArticleView<'a>(&'a Article, &'a Site);
 
 impl<'a> Render for ArticleView<'a> {
     fn render(&self) -> Markup {
         let article = self.0;
+        let rendered = rendered(article, self.1.extensions);
         html! {
             article {
-                h1 {
-                    a href={ "/article/" (article.id) } (article.title)
+                (ArticleHeading(article, self.1))
+                section { p (PreEscaped(&rendered.body)) }
+            }
+        }
+    }
+}
+
+/// Links to the previous and next articles and to related ones.
+pub struct NavigationView<'a>(&'a Navigation, &'a Site);
+
+impl<'a> Render for NavigationView<'a> {
+    fn render(&self) -> Markup {
+        let navigation = self.0;
+        let t = self.1.settings.locale.messages();
+        html! {
+            @if navigation.previous.is_some() || navigation.next.is_some() {
+                nav.articles {
+                    @if let Some(ref previous) = navigation.previous {
+                        a.prev href={ "/article/" (previous.id) } rel="prev" {
+                            span.label (t.previous_article)
+                            (previous.title)
+                        }
+                    }
+                    @if let Some(ref next) = navigation.next {
+                        a.next href={ "/article/" (next.id) } rel="next" {
+                            span.label (t.next_article)
+                            (next.title)
+                        }
+                    }
                 }
-                footer {
-                    span {
-                        "Posted on "
-                        time datetime={ (article.created_at.format("%F")) } {
-                            (article.created_at.format("%Y年%-m月%-d日"))
+            }
+            @if !navigation.related.is_empty() {
+                section.related {
+                    h2 (t.related_articles)
+                    ul {
+                        @for link in &navigation.related {
+                            li {
+                                a href={ "/article/" (link.id) } (link.title)
+                            }
                         }
                     }
                 }
-                section { p (Markdown(&article.body)) }
             }
         }
     }
 }
 
-fn abbreviate_body(article: &mut Article) {
-    let body = {
-        let v: Vec<&str> = article.body.splitn(2, "<!-- more -->").collect();
-        if v.len() == 1 {
-            return;
+/// The parts of the series an article belongs to, marking the article itself.
+pub struct SeriesBox<'a>(&'a SeriesParts, i32, &'a Site);
+
+impl<'a> Render for SeriesBox<'a> {
+    fn render(&self) -> Markup {
+        let series = self.0;
+        let t = self.2.settings.locale.messages();
+        html! {
+            aside.series {
+                h2 {
+                    span.label (t.series)
+                    a href={ "/series/" (series.series.slug) } (series.series.title)
+                }
+                @if let Some(part) = series.position(self.1) {
+                    p.part (t.series_part(part, series.parts.len()))
+                }
+                ol {
+                    @for link in &series.parts {
+                        li {
+                            @if link.id == self.1 {
+                                strong (link.title)
+                            } @else {
+                                a href={ "/article/" (link.id) } (link.title)
+                            }
+                        }
+                    }
+                }
+            }
         }
-        format!("{}\n[続きを読む](/article/{})", v[0], article.id)
-    };
-    article.body = body;
+    }
+}
+
+/// An article as listed on the index: its summary followed by a link to the
+/// full text, or the whole body when it is short.
+pub struct ArticleSummaryView<'a>(&'a Article, &'a Site);
+
+impl<'a> Render for ArticleSummaryView<'a> {
+    fn render(&self) -> Markup {
+        let article = self.0;
+        let rendered = rendered(article, self.1.extensions);
+        let t = self.1.settings.locale.messages();
+        html! {
+            article {
+                @if article.pinned {
+                    p.pinned (t.pinned)
+                }
+                (ArticleHeading(article, self.1))
+                section {
+                    @match rendered.summary {
+                        Some(ref summary) => {
+                            p (PreEscaped(summary))
+                            p.more { a href={ "/article/" (article.id) } (t.read_more) }
+                        }
+                        None => {
+                            p (PreEscaped(&rendered.body))
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Links to the featured articles, shown above the index on the front page.
+pub struct FeaturedView<'a>(&'a [ArticleLink], &'a Site);
+
+impl<'a> Render for FeaturedView<'a> {
+    fn render(&self) -> Markup {
+        let settings = &self.1.settings;
+        let t = settings.locale.messages();
+        let local = |link: &ArticleLink| link.created_at.with_timezone(&settings.timezone);
+        html! {
+            @if !self.0.is_empty() {
+                section.featured {
+                    h2 (t.featured_articles)
+                    ul {
+                        @for link in self.0 {
+                            li {
+                                a href={ "/article/" (link.id) } (link.title)
+                                time datetime={ (local(link).format("%F")) } {
+                                    (local(link).format(t.date_format))
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
 }
 
 #[get("/<file..>", rank = 99)]
-pub fn static_file(file: PathBuf) -> Option<NamedFile> {
-    NamedFile::open(Path::new("static/").join(file)).ok()
+pub fn static_file(
+    file: PathBuf,
+    site: CurrentSite,
+    conditional: Conditional,
+) -> Result<Option<Cached<NamedFile>>> {
+    // Fingerprinted URLs never change their content, the plain paths may.
+    let (file, cache_control) = match site.assets.resolve(&file) {
+        Some(original) => (original, assets::IMMUTABLE),
+        None => (file, site.static_cache_control.as_str()),
+    };
+    let file = match site.assets.find(&file).map(NamedFile::open) {
+        Some(Ok(file)) => file,
+        _ => return Ok(None),
+    };
+    let validator = Validator::for_file(file.file())?;
+    let response = conditional.respond(validator, cache_control, || Ok(file))?;
+    Ok(Some(response))
 }
 
-#[get("/")]
-pub fn index_page(conn: Connection) -> Result<Markup> {
-    let mut articles = Article::list(&*conn)?;
-    for mut article in articles.iter_mut() {
-        abbreviate_body(&mut article);
+/// Links to the previous and next pages of the index.
+pub struct Pagination {
+    pub page: i64,
+    pub pages: i64,
+    messages: &'static Messages,
+}
+
+impl Pagination {
+    pub fn url(page: i64) -> String {
+        if page == 1 {
+            "/".to_owned()
+        } else {
+            format!("/page/{}", page)
+        }
     }
 
-    Ok(html! {
-        (Header(""))
-        main {
-            @for article in articles {
-                (ArticleView(&article))
+    pub fn prev_url(&self) -> Option<String> {
+        if self.page > 1 {
+            Some(Pagination::url(self.page - 1))
+        } else {
+            None
+        }
+    }
+
+    pub fn next_url(&self) -> Option<String> {
+        if self.page < self.pages {
+            Some(Pagination::url(self.page + 1))
+        } else {
+            None
+        }
+    }
+}
+
+impl Render for Pagination {
+    fn render(&self) -> Markup {
+        html! {
+            @if self.pages > 1 {
+                nav.pagination {
+                    @if let Some(url) = self.prev_url() {
+                        a.prev href=(url) rel="prev" (self.messages.previous_page)
+                    }
+                    @if let Some(url) = self.next_url() {
+                        a.next href=(url) rel="next" (self.messages.next_page)
+                    }
+                }
             }
         }
-    })
+    }
+}
+
+/// Number of index pages needed for `count` articles.
+pub fn page_count(count: i64, site: &Site) -> i64 {
+    let per_page = site.settings.posts_per_page;
+    cmp::max(1, (count + per_page - 1) / per_page)
+}
+
+/// Renders a page of the index, with the featured articles on the first. Shared by
+/// the server and the static site generator.
+pub fn render_index(
+    articles: Vec<Article>,
+    featured: &[ArticleLink],
+    page: i64,
+    pages: i64,
+    site: &Site,
+) -> Result<Markup> {
+    let description = &site.settings.description;
+    let meta = PageMeta {
+        description: if description.is_empty() {
+            None
+        } else {
+            Some(description)
+        },
+        url: site.url(&Pagination::url(page)),
+        ..PageMeta::titled("")
+    };
+    let pagination = Pagination {
+        page: page,
+        pages: pages,
+        messages: site.settings.locale.messages(),
+    };
+    let content = site.theme.listing(site, &articles, featured, &pagination)?;
+    site.theme.layout(site, &meta, content)
+}
+
+pub fn render_article(article: &Article, navigation: &Navigation, site: &Site) -> Result<Markup> {
+    let description = summary::description(article, site.extensions);
+    let meta = PageMeta::for_article(article, &description, site);
+    let content = site.theme.article(site, article, navigation)?;
+    site.theme.layout(site, &meta, content)
+}
+
+/// Renders the landing page of a series with its published articles in order.
+pub fn render_series(series: &Series, articles: &[Article], site: &Site) -> Result<Markup> {
+    let meta = PageMeta {
+        description: if series.description.is_empty() {
+            None
+        } else {
+            Some(&series.description)
+        },
+        url: site.url(&format!("/series/{}", series.slug)),
+        ..PageMeta::titled(&series.title)
+    };
+    let content = site.theme.series(site, series, articles)?;
+    site.theme.layout(site, &meta, content)
+}
+
+/// Renders an error page, falling back to the built-in theme when the site's
+/// fails, since the error may well be in the theme.
+fn render_error(site: &Site, status: u16, message: &str) -> Markup {
+    let title = status.to_string();
+    let meta = PageMeta::titled(&title);
+    let render = |theme: &Theme| -> Result<Markup> {
+        let content = theme.error(site, status, message)?;
+        theme.layout(site, &meta, content)
+    };
+    render(&*site.theme)
+        .or_else(|_| render(&DefaultTheme))
+        .unwrap_or_else(|_| html!((message)))
+}
+
+pub fn render_not_found(site: &Site) -> Markup {
+    render_error(site, 404, site.settings.locale.messages().not_found)
+}
+
+#[get("/")]
+pub fn index_page(
+    conn: Connection,
+    site: CurrentSite,
+    conditional: Conditional,
+) -> Result<Option<Cached<Markup>>> {
+    index(&*conn, &site, &conditional, 1)
+}
+
+#[get("/page/<page>")]
+pub fn index_page_n(
+    conn: Connection,
+    site: CurrentSite,
+    conditional: Conditional,
+    page: i64,
+) -> Result<Option<Cached<Markup>>> {
+    index(&*conn, &site, &conditional, page)
+}
+
+fn index(
+    conn: &GenericConnection,
+    site: &Site,
+    conditional: &Conditional,
+    page: i64,
+) -> Result<Option<Cached<Markup>>> {
+    let (count, updated_at) = Article::published_revision(conn)?;
+    let pages = page_count(count, site);
+    if page < 1 || page > pages {
+        return Ok(None);
+    }
+    let validator = site.page_validator(
+        &format!("index-{}-{}", page, count),
+        cmp::max(updated_at, site.settings.updated_at),
+    );
+    let response = conditional.respond(validator, &site.cache_control, || {
+        let per_page = site.settings.posts_per_page;
+        let mut articles = Article::list_published_page(conn, (page - 1) * per_page, per_page)?;
+        for article in &mut articles {
+            prepare(conn, article, site.extensions)?;
+        }
+        let featured = if page == 1 {
+            Article::list_featured(conn, FEATURED_ARTICLES)?
+        } else {
+            Vec::new()
+        };
+        render_index(articles, &featured, page, pages, site)
+    })?;
+    Ok(Some(response))
 }
 
 #[get("/article/<id>")]
-pub fn article_page(conn: Connection, id: i32) -> Result<Markup> {
-    let article = Article::get(&*conn, id)?;
-    Ok(html!{
-        (Header(&article.title))
-        main {
-            (ArticleView(&article))
+pub fn article_page(
+    conn: Connection,
+    site: CurrentSite,
+    conditional: Conditional,
+    id: i32,
+) -> Result<Option<Cached<Markup>>> {
+    match Article::revision(&*conn, id)? {
+        Some((_, false)) => {}
+        _ => return Ok(None),
+    }
+    // The links to other articles change along with the published ones, which
+    // include this article, and with the series.
+    let (count, updated_at) = Article::published_revision(&*conn)?;
+    let (series_count, series_updated_at) = Series::revision(&*conn)?;
+    let validator = site.page_validator(
+        &format!("article-{}-{}-{}", id, count, series_count),
+        cmp::max(cmp::max(updated_at, series_updated_at), site.settings.updated_at),
+    );
+    let response = conditional.respond(validator, &site.cache_control, || {
+        let mut article = Article::get(&*conn, id)?;
+        prepare(&*conn, &mut article, site.extensions)?;
+        let navigation = Article::navigation(&*conn, &article, RELATED_ARTICLES)?;
+        render_article(&article, &navigation, &site)
+    })?;
+    Ok(Some(response))
+}
+
+#[get("/series/<slug>")]
+pub fn series_page(
+    conn: Connection,
+    site: CurrentSite,
+    conditional: Conditional,
+    slug: String,
+) -> Result<Option<Cached<Markup>>> {
+    let series = match Series::find_by_slug(&*conn, &slug)? {
+        Some(series) => series,
+        None => return Ok(None),
+    };
+    if Series::parts(&*conn, series.id)?.is_empty() {
+        return Ok(None);
+    }
+    let (count, updated_at) = Article::published_revision(&*conn)?;
+    let validator = site.page_validator(
+        &format!("series-{}-{}", series.id, count),
+        cmp::max(cmp::max(updated_at, Some(series.updated_at)), site.settings.updated_at),
+    );
+    let response = conditional.respond(validator, &site.cache_control, || {
+        let mut articles = Article::list_published_in_series(&*conn, series.id)?;
+        for article in &mut articles {
+            prepare(&*conn, article, site.extensions)?;
         }
-    })
+        render_series(&series, &articles, &site)
+    })?;
+    Ok(Some(response))
+}
+
+/// The site to render error pages with, which are also shown when it is unavailable.
+fn error_site(request: &Request) -> Site {
+    request
+        .guard::<CurrentSite>()
+        .succeeded()
+        .map(|site| (*site).clone())
+        .unwrap_or_default()
 }
 
 #[error(404)]
-pub fn not_found(_: &Request) -> Markup {
-    html! {
-        (Header("404"))
-        main {
-            section "ページが見つかりません。"
-        }
-    }
+pub fn not_found(request: &Request) -> Markup {
+    render_not_found(&error_site(request))
 }
 
 #[error(500)]
-pub fn internal_error() -> Markup {
-    html! {
-        (Header("500"))
-        main {
-            section "システムエラーが発生しました。"
-        }
-    }
+pub fn internal_error(request: &Request) -> Markup {
+    let site = error_site(request);
+    render_error(&site, 500, site.settings.locale.messages().internal_error)
 }
@@ -0,0 +1,190 @@
+use super::{theme, DefaultTheme, Extensions, Theme};
+use super::assets::Assets;
+use super::caching::Validator;
+use db::setting::Settings;
+use errors::*;
+
+use chrono::{DateTime, TimeZone};
+use postgres::GenericConnection;
+use rocket::{Outcome, Request, State};
+use rocket::config::{Config, ConfigError};
+use rocket::http::Status;
+use rocket::request::{self, FromRequest};
+
+use std::ops::Deref;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+/// Site wide configuration read from Rocket.toml, along with the settings edited
+/// in the admin.
+#[derive(Clone, Debug)]
+pub struct Site {
+    /// Absolute URLs such as canonical links are omitted when its `base_url` is empty.
+    pub settings: Settings,
+    pub extensions: Extensions,
+    /// Font used to draw social preview images. They are disabled when it is unset.
+    pub og_font: Option<PathBuf>,
+    /// Directory for generated files such as social preview images.
+    pub cache_dir: PathBuf,
+    /// `Cache-Control` of public pages.
+    pub cache_control: String,
+    /// `Cache-Control` of files under `static/`.
+    pub static_cache_control: String,
+    /// Header a reverse proxy puts the client's address in, such as `X-Real-IP`.
+    /// The address of the connection is used when it is unset.
+    pub client_ip_header: Option<String>,
+    pub theme: Arc<Theme>,
+    /// The files under `static/` and the theme's static directory.
+    pub assets: Arc<Assets>,
+}
+
+impl Default for Site {
+    fn default() -> Site {
+        Site {
+            settings: Settings::default(),
+            extensions: Extensions::default(),
+            og_font: None,
+            cache_dir: PathBuf::from("cache"),
+            cache_control: "public, max-age=60".to_owned(),
+            static_cache_control: "public, max-age=604800".to_owned(),
+            client_ip_header: None,
+            theme: Arc::new(DefaultTheme),
+            assets: Arc::new(Assets::default()),
+        }
+    }
+}
+
+impl Site {
+    /// Reads the configuration. `base_url` serves as the default of the setting.
+    pub fn from_config(config: &Config) -> Result<Site> {
+        let mut site = Site::default();
+        if let Some(base_url) = optional_str(config, "base_url")? {
+            site.settings.base_url = base_url.trim_right_matches('/').to_owned();
+        }
+        site.extensions = Extensions::from_config(config)?;
+        site.og_font = optional_str(config, "og_font")?.map(PathBuf::from);
+        if let Some(cache_dir) = optional_str(config, "cache_dir")? {
+            site.cache_dir = PathBuf::from(cache_dir);
+        }
+        if let Some(cache_control) = optional_str(config, "cache_control")? {
+            site.cache_control = cache_control;
+        }
+        if let Some(cache_control) = optional_str(config, "static_cache_control")? {
+            site.static_cache_control = cache_control;
+        }
+        site.client_ip_header = optional_str(config, "client_ip_header")?;
+        site.assets = Arc::new(Assets::load(theme::asset_dirs(config)?)?);
+        site.theme = theme::from_config(config, site.assets.clone())?;
+        Ok(site)
+    }
+
+    /// Reads the configuration and the stored settings.
+    pub fn load(config: &Config, conn: &GenericConnection) -> Result<Site> {
+        let mut site = Site::from_config(config)?;
+        site.settings = Settings::load(conn, site.settings)?;
+        Ok(site)
+    }
+
+    /// Makes the validator of a page, which also changes with the static files and
+    /// the theme the page is rendered with, and with how Markdown is rendered.
+    pub fn page_validator<Tz: TimeZone>(
+        &self,
+        name: &str,
+        last_modified: Option<DateTime<Tz>>,
+    ) -> Validator {
+        let tag = format!(
+            "{}-{}-{}-{}",
+            name,
+            self.assets.version(),
+            self.theme.version(),
+            self.extensions.fingerprint()
+        );
+        Validator::new(&tag, last_modified)
+    }
+
+    /// Makes an absolute URL from a site absolute path like `/article/1`. URLs that
+    /// already have a scheme are returned as they are, and ones starting with `//`
+    /// get the scheme of `base_url`.
+    pub fn url(&self, path: &str) -> Option<String> {
+        if path.contains("://") {
+            Some(path.to_owned())
+        } else if self.settings.base_url.is_empty() {
+            None
+        } else if path.starts_with("//") {
+            let scheme = self.settings.base_url.split(':').next().unwrap_or("https");
+            Some(format!("{}:{}", scheme, path))
+        } else if path.starts_with('/') {
+            Some(format!("{}{}", self.settings.base_url, path))
+        } else {
+            Some(format!("{}/{}", self.settings.base_url, path))
+        }
+    }
+}
+
+/// The site as managed state. Saving the settings replaces it as a whole, so each
+/// request renders with the snapshot it started with.
+pub struct SharedSite(RwLock<Arc<Site>>);
+
+impl SharedSite {
+    pub fn new(site: Site) -> SharedSite {
+        SharedSite(RwLock::new(Arc::new(site)))
+    }
+
+    pub fn get(&self) -> Arc<Site> {
+        self.0.read().unwrap().clone()
+    }
+
+    pub fn set_settings(&self, settings: Settings) {
+        let mut site = self.0.write().unwrap();
+        let mut updated = (**site).clone();
+        updated.settings = settings;
+        *site = Arc::new(updated);
+    }
+}
+
+/// Request guard for the current snapshot of the site.
+pub struct CurrentSite(Arc<Site>);
+
+impl Deref for CurrentSite {
+    type Target = Site;
+
+    fn deref(&self) -> &Site {
+        &self.0
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for CurrentSite {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<CurrentSite, ()> {
+        match request.guard::<State<SharedSite>>() {
+            Outcome::Success(shared) => Outcome::Success(CurrentSite(shared.get())),
+            _ => Outcome::Failure((Status::InternalServerError, ())),
+        }
+    }
+}
+
+fn optional_str(config: &Config, name: &str) -> Result<Option<String>> {
+    match config.get_str(name) {
+        Ok(value) => Ok(Some(value.to_owned())),
+        Err(ConfigError::NotFound) => Ok(None),
+        Err(_) => bail!("{} must be a string", name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url() {
+        let mut site = Site::default();
+        site.settings.base_url = "https://example.com".to_owned();
+        assert_eq!(site.url("/article/1"), Some("https://example.com/article/1".to_owned()));
+        assert_eq!(site.url("a.png"), Some("https://example.com/a.png".to_owned()));
+        assert_eq!(site.url("http://cdn/a.png"), Some("http://cdn/a.png".to_owned()));
+        assert_eq!(site.url("//cdn/a.png"), Some("https://cdn/a.png".to_owned()));
+        site.settings.base_url = String::new();
+        assert_eq!(site.url("/article/1"), None);
+    }
+}
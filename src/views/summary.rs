@@ -0,0 +1,84 @@
+use super::markdown::{plain_text, Extensions};
+use db::article::Article;
+
+/// Maximum length in characters of automatic excerpts.
+pub const EXCERPT_LENGTH: usize = 140;
+
+const MORE_MARKER: &'static str = "<!-- more -->";
+
+/// What the index shows in place of an article body.
+#[derive(Debug, PartialEq)]
+pub enum Summary<'a> {
+    /// Markdown written as a summary or before the `<!-- more -->` marker.
+    Markdown(&'a str),
+    /// Plain text excerpt of a body too long to show in full.
+    Excerpt(String),
+    /// The body is short enough to show as it is.
+    Full,
+}
+
+pub fn summary(article: &Article, extensions: Extensions) -> Summary {
+    if let Some(ref summary) = article.summary {
+        if !summary.trim().is_empty() {
+            return Summary::Markdown(summary);
+        }
+    }
+    if let Some(i) = article.body.find(MORE_MARKER) {
+        return Summary::Markdown(&article.body[..i]);
+    }
+    let text = plain_text(&article.body, extensions);
+    if text.chars().count() <= EXCERPT_LENGTH {
+        Summary::Full
+    } else {
+        Summary::Excerpt(excerpt(&text, EXCERPT_LENGTH))
+    }
+}
+
+/// Plain text description of an article for `<meta name="description">`.
+pub fn description(article: &Article, extensions: Extensions) -> String {
+    let source = match article.summary {
+        Some(ref summary) if !summary.trim().is_empty() => summary,
+        _ => &article.body,
+    };
+    excerpt(&plain_text(source, extensions), EXCERPT_LENGTH)
+}
+
+/// Cuts `text` to at most `limit` characters, preferably after the last full
+/// sentence. Japanese sentences end with `。！？`; latin ones with `.!?` followed
+/// by a space. Falls back to a hard cut marked with an ellipsis.
+pub fn excerpt(text: &str, limit: usize) -> String {
+    let end = match text.char_indices().nth(limit) {
+        Some((end, _)) => end,
+        None => return text.to_owned(),
+    };
+    let mut boundary = None;
+    let mut chars = text[..end].char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let next = chars.peek().map(|&(_, c)| c).or_else(|| text[end..].chars().next());
+        let is_end = match c {
+            '。' | '！' | '？' | '．' => true,
+            '.' | '!' | '?' => next.map(|c| c.is_whitespace()).unwrap_or(true),
+            _ => false,
+        };
+        if is_end {
+            boundary = Some(i + c.len_utf8());
+        }
+    }
+    match boundary {
+        Some(boundary) if boundary >= end / 2 => text[..boundary].to_owned(),
+        _ => format!("{}…", text[..end].trim_right()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn excerpt_at_sentence() {
+        assert_eq!(excerpt("短い文。", 10), "短い文。");
+        assert_eq!(excerpt("一つ目の文です。二つ目の文です。三つ目", 12), "一つ目の文です。");
+        assert_eq!(excerpt("First one. Second one. Third", 24), "First one. Second one.");
+        assert_eq!(excerpt("Version 1.2 is out", 12), "Version 1.2…");
+    }
+}
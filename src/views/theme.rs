@@ -0,0 +1,662 @@
+//! Themes render the layout and the article, listing, series and error pages of the
+//! public site. The built-in theme is written with maud. Others are directories of
+//! Handlebars templates and static files, loaded at startup so that they can be
+//! changed without recompiling:
+//!
+//! ```text
+//! themes/<name>/templates/{layout,article,listing,series,error}.hbs
+//! themes/<name>/static/
+//! ```
+//!
+//! Pages a theme has no template for are rendered by the built-in theme, and files
+//! under its `static/` hide the ones with the same path under `static/`.
+
+use super::{rendered, ArticleSummaryView, ArticleView, FeaturedView, Footer, Head, Header,
+            NavigationView, PageMeta, Pagination, SeriesBox, Site};
+use super::assets::{self, Assets};
+use db::article::{Article, ArticleLink, Navigation};
+use db::series::{Series, SeriesParts};
+use errors::*;
+use locale::Messages;
+
+use handlebars::{Handlebars, Helper, RenderContext, RenderError};
+use maud::{html, Markup, PreEscaped, Render};
+use rocket::config::{Config, ConfigError, Environment};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use std::fmt;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const THEMES_DIR: &'static str = "themes";
+const TEMPLATES: &'static [&'static str] = &["layout", "article", "listing", "series", "error"];
+
+pub trait Theme: fmt::Debug + Send + Sync {
+    /// Wraps the content of a page into the whole document.
+    fn layout(&self, site: &Site, meta: &PageMeta, content: Markup) -> Result<Markup>;
+
+    /// An article followed by the series it is part of and links to other
+    /// articles.
+    fn article(
+        &self,
+        site: &Site,
+        article: &Article,
+        navigation: &Navigation,
+    ) -> Result<Markup>;
+
+    /// A page of the index. `featured` is empty but on the first page.
+    fn listing(
+        &self,
+        site: &Site,
+        articles: &[Article],
+        featured: &[ArticleLink],
+        pagination: &Pagination,
+    ) -> Result<Markup>;
+
+    /// The landing page of a series with its published articles in order.
+    fn series(&self, site: &Site, series: &Series, articles: &[Article]) -> Result<Markup>;
+
+    fn error(&self, site: &Site, status: u16, message: &str) -> Result<Markup>;
+
+    /// Identifies the templates, changing whenever one of them does, for the
+    /// validators of pages.
+    fn version(&self) -> String;
+}
+
+#[derive(Debug)]
+pub struct DefaultTheme;
+
+impl Theme for DefaultTheme {
+    fn layout(&self, site: &Site, meta: &PageMeta, content: Markup) -> Result<Markup> {
+        Ok(html! {
+            (Header(site, meta))
+            main (content)
+            (Footer(site))
+        })
+    }
+
+    fn article(
+        &self,
+        site: &Site,
+        article: &Article,
+        navigation: &Navigation,
+    ) -> Result<Markup> {
+        Ok(html! {
+            (ArticleView(article, site))
+            @if let Some(ref series) = navigation.series {
+                (SeriesBox(series, article.id, site))
+            }
+            (NavigationView(navigation, site))
+        })
+    }
+
+    fn listing(
+        &self,
+        site: &Site,
+        articles: &[Article],
+        featured: &[ArticleLink],
+        pagination: &Pagination,
+    ) -> Result<Markup> {
+        Ok(html! {
+            (FeaturedView(featured, site))
+            @for article in articles {
+                (ArticleSummaryView(article, site))
+            }
+            (pagination.render())
+        })
+    }
+
+    fn series(&self, site: &Site, series: &Series, articles: &[Article]) -> Result<Markup> {
+        let t = site.settings.locale.messages();
+        Ok(html! {
+            section.series {
+                h1 {
+                    span.label (t.series)
+                    (series.title)
+                }
+                @if !series.description.is_empty() {
+                    p (series.description)
+                }
+            }
+            @for (i, article) in articles.iter().enumerate() {
+                p.part (t.series_part(i + 1, articles.len()))
+                (ArticleSummaryView(article, site))
+            }
+        })
+    }
+
+    fn error(&self, _site: &Site, _status: u16, message: &str) -> Result<Markup> {
+        Ok(html! {
+            section (message)
+        })
+    }
+
+    fn version(&self) -> String {
+        "default".to_owned()
+    }
+}
+
+/// A theme of Handlebars templates. Besides the data described below, every
+/// template gets `site` (name, description, base_url, footer and lang) and the
+/// messages of the site's locale as `t`, and can use `{{asset "/css/style.css"}}`
+/// for the fingerprinted URL of a static file.
+pub struct TemplateTheme {
+    dir: PathBuf,
+    assets: Arc<Assets>,
+    dev_mode: bool,
+    templates: Handlebars,
+    version: String,
+}
+
+impl fmt::Debug for TemplateTheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TemplateTheme")
+            .field("dir", &self.dir)
+            .finish()
+    }
+}
+
+impl TemplateTheme {
+    /// Loads the templates of a theme directory. In `dev_mode` they are read again
+    /// on every render.
+    pub fn load(dir: &Path, assets: Arc<Assets>, dev_mode: bool) -> Result<TemplateTheme> {
+        Ok(TemplateTheme {
+            dir: dir.to_owned(),
+            templates: TemplateTheme::registry(dir, assets.clone())?,
+            assets: assets,
+            dev_mode: dev_mode,
+            version: templates_version(dir)?,
+        })
+    }
+
+    fn registry(dir: &Path, assets: Arc<Assets>) -> Result<Handlebars> {
+        let mut templates = Handlebars::new();
+        for &name in TEMPLATES {
+            let path = dir.join("templates").join(format!("{}.hbs", name));
+            if path.is_file() {
+                templates
+                    .register_template_file(name, &path)
+                    .chain_err(|| format!("invalid template {}", path.display()))?;
+            }
+        }
+        templates.register_helper(
+            "asset",
+            Box::new(
+                move |helper: &Helper,
+                      _: &Handlebars,
+                      rc: &mut RenderContext|
+                      -> ::std::result::Result<(), RenderError> {
+                    let path = helper
+                        .param(0)
+                        .and_then(|param| param.value().as_str())
+                        .unwrap_or("");
+                    rc.writer().write_all(assets.url(path).as_bytes())?;
+                    Ok(())
+                },
+            ),
+        );
+        Ok(templates)
+    }
+
+    fn has_template(&self, name: &str) -> bool {
+        if self.dev_mode {
+            self.dir
+                .join("templates")
+                .join(format!("{}.hbs", name))
+                .is_file()
+        } else {
+            self.templates.get_template(name).is_some()
+        }
+    }
+
+    fn render<T: Serialize>(&self, name: &str, data: &T) -> Result<Markup> {
+        let reloaded;
+        let templates = if self.dev_mode {
+            reloaded = TemplateTheme::registry(&self.dir, self.assets.clone())?;
+            &reloaded
+        } else {
+            &self.templates
+        };
+        templates
+            .render(name, data)
+            .map(PreEscaped)
+            .chain_err(|| format!("failed to render {} of {}", name, self.dir.display()))
+    }
+}
+
+#[derive(Serialize)]
+struct SiteData<'a> {
+    name: &'a str,
+    description: &'a str,
+    base_url: &'a str,
+    footer: &'a str,
+    lang: &'static str,
+}
+
+impl<'a> SiteData<'a> {
+    fn new(site: &'a Site) -> SiteData<'a> {
+        let settings = &site.settings;
+        SiteData {
+            name: &settings.site_name,
+            description: &settings.description,
+            base_url: &settings.base_url,
+            footer: &settings.footer,
+            lang: settings.locale.code(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ArticleData<'a> {
+    id: i32,
+    url: String,
+    title: &'a str,
+    author: &'a str,
+    pinned: bool,
+    /// RFC 3339, for `<time datetime>`.
+    published: String,
+    /// Formatted for the site's locale.
+    date: String,
+    body: String,
+    /// HTML shown on the index instead of the body, if any.
+    summary: Option<String>,
+}
+
+impl<'a> ArticleData<'a> {
+    fn new(article: &'a Article, site: &Site) -> ArticleData<'a> {
+        let settings = &site.settings;
+        let created_at = article.created_at.with_timezone(&settings.timezone);
+        let rendered = rendered(article, site.extensions).into_owned();
+        ArticleData {
+            id: article.id,
+            url: format!("/article/{}", article.id),
+            title: &article.title,
+            author: &article.author.name,
+            pinned: article.pinned,
+            published: created_at.to_rfc3339(),
+            date: created_at
+                .format(settings.locale.messages().date_format)
+                .to_string(),
+            body: rendered.body,
+            summary: rendered.summary,
+        }
+    }
+}
+
+/// Another article linked from an article page.
+#[derive(Serialize)]
+struct LinkData<'a> {
+    id: i32,
+    url: String,
+    title: &'a str,
+    published: String,
+    date: String,
+}
+
+impl<'a> LinkData<'a> {
+    fn new(link: &'a ArticleLink, site: &Site) -> LinkData<'a> {
+        let settings = &site.settings;
+        let created_at = link.created_at.with_timezone(&settings.timezone);
+        LinkData {
+            id: link.id,
+            url: format!("/article/{}", link.id),
+            title: &link.title,
+            published: created_at.to_rfc3339(),
+            date: created_at
+                .format(settings.locale.messages().date_format)
+                .to_string(),
+        }
+    }
+}
+
+/// The series an article page belongs to: its `title`, `url` and `description`,
+/// the `part` the article is if published, the number of `parts` and the
+/// published `articles` in order.
+#[derive(Serialize)]
+struct SeriesData<'a> {
+    slug: &'a str,
+    url: String,
+    title: &'a str,
+    description: &'a str,
+    part: Option<usize>,
+    parts: usize,
+    /// "Part 2 of 5" in the site's language, if the article is published.
+    label: Option<String>,
+    articles: Vec<LinkData<'a>>,
+}
+
+impl<'a> SeriesData<'a> {
+    fn new(
+        series: &'a Series,
+        part: Option<usize>,
+        parts: usize,
+        articles: Vec<LinkData<'a>>,
+        site: &Site,
+    ) -> SeriesData<'a> {
+        SeriesData {
+            slug: &series.slug,
+            url: format!("/series/{}", series.slug),
+            title: &series.title,
+            description: &series.description,
+            part: part,
+            parts: parts,
+            label: part.map(|part| site.settings.locale.messages().series_part(part, parts)),
+            articles: articles,
+        }
+    }
+
+    fn for_article(parts: &'a SeriesParts, article: &Article, site: &Site) -> SeriesData<'a> {
+        SeriesData::new(
+            &parts.series,
+            parts.position(article.id),
+            parts.parts.len(),
+            parts
+                .parts
+                .iter()
+                .map(|link| LinkData::new(link, site))
+                .collect(),
+            site,
+        )
+    }
+}
+
+/// `layout.hbs`: `title` of the document, `head` with the meta tags of the page
+/// (including `<title>`) and the `content` of the page as HTML.
+#[derive(Serialize)]
+struct LayoutPage<'a> {
+    site: SiteData<'a>,
+    t: &'static Messages,
+    title: String,
+    head: String,
+    content: String,
+}
+
+/// `article.hbs`: the `article`, the `previous` and `next` articles if any, the
+/// `related` ones and the `series` the article is part of, if any.
+#[derive(Serialize)]
+struct ArticlePage<'a> {
+    site: SiteData<'a>,
+    t: &'static Messages,
+    article: ArticleData<'a>,
+    previous: Option<LinkData<'a>>,
+    next: Option<LinkData<'a>>,
+    related: Vec<LinkData<'a>>,
+    series: Option<SeriesData<'a>>,
+}
+
+/// `listing.hbs`: the `articles` of the page, the `featured` ones on the first page
+/// and `pagination` with `page`, `pages`, `prev_url` and `next_url`.
+#[derive(Serialize)]
+struct ListingPage<'a> {
+    site: SiteData<'a>,
+    t: &'static Messages,
+    articles: Vec<ArticleData<'a>>,
+    featured: Vec<LinkData<'a>>,
+    pagination: PaginationData,
+}
+
+#[derive(Serialize)]
+struct PaginationData {
+    page: i64,
+    pages: i64,
+    prev_url: Option<String>,
+    next_url: Option<String>,
+}
+
+/// `series.hbs`: the `series`, without `part`, `label` and `articles`, and its
+/// `articles` in full.
+#[derive(Serialize)]
+struct SeriesPage<'a> {
+    site: SiteData<'a>,
+    t: &'static Messages,
+    series: SeriesData<'a>,
+    articles: Vec<ArticleData<'a>>,
+}
+
+/// `error.hbs`: the HTTP `status` and a `message`.
+#[derive(Serialize)]
+struct ErrorPage<'a> {
+    site: SiteData<'a>,
+    t: &'static Messages,
+    status: u16,
+    message: &'a str,
+}
+
+impl Theme for TemplateTheme {
+    fn layout(&self, site: &Site, meta: &PageMeta, content: Markup) -> Result<Markup> {
+        if !self.has_template("layout") {
+            return DefaultTheme.layout(site, meta, content);
+        }
+        self.render(
+            "layout",
+            &LayoutPage {
+                site: SiteData::new(site),
+                t: site.settings.locale.messages(),
+                title: meta.document_title(&site.settings.site_name),
+                head: Head(site, meta).render().into_string(),
+                content: content.into_string(),
+            },
+        )
+    }
+
+    fn article(
+        &self,
+        site: &Site,
+        article: &Article,
+        navigation: &Navigation,
+    ) -> Result<Markup> {
+        if !self.has_template("article") {
+            return DefaultTheme.article(site, article, navigation);
+        }
+        self.render(
+            "article",
+            &ArticlePage {
+                site: SiteData::new(site),
+                t: site.settings.locale.messages(),
+                article: ArticleData::new(article, site),
+                previous: navigation
+                    .previous
+                    .as_ref()
+                    .map(|link| LinkData::new(link, site)),
+                next: navigation.next.as_ref().map(|link| LinkData::new(link, site)),
+                related: navigation
+                    .related
+                    .iter()
+                    .map(|link| LinkData::new(link, site))
+                    .collect(),
+                series: navigation
+                    .series
+                    .as_ref()
+                    .map(|parts| SeriesData::for_article(parts, article, site)),
+            },
+        )
+    }
+
+    fn listing(
+        &self,
+        site: &Site,
+        articles: &[Article],
+        featured: &[ArticleLink],
+        pagination: &Pagination,
+    ) -> Result<Markup> {
+        if !self.has_template("listing") {
+            return DefaultTheme.listing(site, articles, featured, pagination);
+        }
+        self.render(
+            "listing",
+            &ListingPage {
+                site: SiteData::new(site),
+                t: site.settings.locale.messages(),
+                articles: articles
+                    .iter()
+                    .map(|article| ArticleData::new(article, site))
+                    .collect(),
+                featured: featured
+                    .iter()
+                    .map(|link| LinkData::new(link, site))
+                    .collect(),
+                pagination: PaginationData {
+                    page: pagination.page,
+                    pages: pagination.pages,
+                    prev_url: pagination.prev_url(),
+                    next_url: pagination.next_url(),
+                },
+            },
+        )
+    }
+
+    fn series(&self, site: &Site, series: &Series, articles: &[Article]) -> Result<Markup> {
+        if !self.has_template("series") {
+            return DefaultTheme.series(site, series, articles);
+        }
+        self.render(
+            "series",
+            &SeriesPage {
+                site: SiteData::new(site),
+                t: site.settings.locale.messages(),
+                series: SeriesData::new(series, None, articles.len(), Vec::new(), site),
+                articles: articles
+                    .iter()
+                    .map(|article| ArticleData::new(article, site))
+                    .collect(),
+            },
+        )
+    }
+
+    fn error(&self, site: &Site, status: u16, message: &str) -> Result<Markup> {
+        if !self.has_template("error") {
+            return DefaultTheme.error(site, status, message);
+        }
+        self.render(
+            "error",
+            &ErrorPage {
+                site: SiteData::new(site),
+                t: site.settings.locale.messages(),
+                status: status,
+                message: message,
+            },
+        )
+    }
+
+    fn version(&self) -> String {
+        if self.dev_mode {
+            // The templates may have changed since they were loaded.
+            templates_version(&self.dir).unwrap_or_else(|_| self.version.clone())
+        } else {
+            self.version.clone()
+        }
+    }
+}
+
+/// Hashes the names and contents of the templates of a theme directory.
+fn templates_version(dir: &Path) -> Result<String> {
+    let mut hasher = Sha256::default();
+    for &name in TEMPLATES {
+        let path = dir.join("templates").join(format!("{}.hbs", name));
+        if path.is_file() {
+            let mut data = Vec::new();
+            File::open(&path)?.read_to_end(&mut data)?;
+            hasher.input(format!("{} {}\n", name, data.len()).as_bytes());
+            hasher.input(&data);
+        }
+    }
+    Ok(assets::short_hash(&hasher.result()))
+}
+
+/// The directory of the theme selected by `theme`, which is `default` for the
+/// built-in theme or the name of a directory under `themes/`.
+fn theme_dir(config: &Config) -> Result<Option<PathBuf>> {
+    match config.get_str("theme") {
+        Ok("default") | Err(ConfigError::NotFound) => Ok(None),
+        Ok(name) => {
+            let dir = Path::new(THEMES_DIR).join(name);
+            if !dir.is_dir() {
+                bail!("theme {} is not found in {}/", name, THEMES_DIR);
+            }
+            Ok(Some(dir))
+        }
+        Err(_) => bail!("theme must be a string"),
+    }
+}
+
+/// Directories of the files served at the root of the site, the theme's first.
+pub fn asset_dirs(config: &Config) -> Result<Vec<PathBuf>> {
+    let mut dirs = Vec::new();
+    if let Some(dir) = theme_dir(config)? {
+        dirs.push(dir.join("static"));
+    }
+    dirs.push(PathBuf::from("static"));
+    Ok(dirs)
+}
+
+/// Loads the theme selected by `theme`. Templates are reloaded on change in the
+/// development environment.
+pub fn from_config(config: &Config, assets: Arc<Assets>) -> Result<Arc<Theme>> {
+    match theme_dir(config)? {
+        Some(dir) => {
+            let dev_mode = config.environment == Environment::Development;
+            Ok(Arc::new(TemplateTheme::load(&dir, assets, dev_mode)?))
+        }
+        None => Ok(Arc::new(DefaultTheme)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+    use std::{env, process};
+
+    #[test]
+    fn templates() {
+        let dir = env::temp_dir().join(format!("zakki-theme-{}", process::id()));
+        fs::create_dir_all(dir.join("templates")).unwrap();
+        File::create(dir.join("templates/layout.hbs"))
+            .and_then(|mut file| {
+                file.write_all(b"<html lang=\"{{site.lang}}\"><title>{{title}}</title>\
+                                 <link href=\"{{asset \"/css/style.css\"}}\">{{{content}}}")
+            })
+            .unwrap();
+
+        let site = Site::default();
+        let theme = TemplateTheme::load(&dir, Arc::new(Assets::default()), false).unwrap();
+        let content = theme.error(&site, 404, "<missing>").unwrap();
+        assert_eq!(content.clone().into_string(), "<section>&lt;missing&gt;</section>");
+        let page = theme.layout(&site, &PageMeta::titled("404"), content).unwrap();
+        assert_eq!(
+            page.into_string(),
+            "<html lang=\"ja\"><title>404 - 雑記</title><link href=\"/css/style.css\">\
+             <section>&lt;missing&gt;</section>"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn version() {
+        let dir = env::temp_dir().join(format!("zakki-theme-version-{}", process::id()));
+        fs::create_dir_all(dir.join("templates")).unwrap();
+        let write_layout = |content: &[u8]| {
+            File::create(dir.join("templates/layout.hbs"))
+                .and_then(|mut file| file.write_all(content))
+                .unwrap();
+        };
+        write_layout(b"{{{content}}}");
+        let load = |dev_mode| TemplateTheme::load(&dir, Arc::new(Assets::default()), dev_mode).unwrap();
+        let theme = load(false);
+        let dev_theme = load(true);
+        let version = theme.version();
+        assert_eq!(load(false).version(), version);
+        assert_ne!(version, DefaultTheme.version());
+
+        write_layout(b"<main>{{{content}}}</main>");
+        assert_ne!(load(false).version(), version);
+        // Loaded templates stay as they are until a restart but in development.
+        assert_eq!(theme.version(), version);
+        assert_ne!(dev_theme.version(), version);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
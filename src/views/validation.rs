@@ -0,0 +1,75 @@
+//! Checks of submitted form fields. Every invalid field gets a message, so that the
+//! form can be shown again with the input kept and each message next to its field.
+
+use maud::{html, Markup, Render};
+
+/// Messages telling what to fix in a form, by field name.
+#[derive(Debug, Default, PartialEq)]
+pub struct FieldErrors(Vec<(&'static str, String)>);
+
+impl FieldErrors {
+    /// Adds `message` for `field` unless `valid`. Only the first message of a field
+    /// is kept.
+    pub fn check<M: Into<String>>(&mut self, field: &'static str, valid: bool, message: M) {
+        if !valid && self.get(field).is_none() {
+            self.0.push((field, message.into()));
+        }
+    }
+
+    pub fn get(&self, field: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|&&(name, _)| name == field)
+            .map(|&(_, ref message)| &message[..])
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// The message of a field, if any, shown next to it.
+pub struct FieldError<'a>(pub Option<&'a FieldErrors>, pub &'static str);
+
+impl<'a> Render for FieldError<'a> {
+    fn render(&self) -> Markup {
+        html! {
+            @if let Some(message) = self.0.and_then(|errors| errors.get(self.1)) {
+                p.field-error (message)
+            }
+        }
+    }
+}
+
+/// Whether a value has anything but whitespace.
+pub fn present(value: &str) -> bool {
+    !value.trim().is_empty()
+}
+
+/// Whether a value has at most `max` characters, as users count them rather than
+/// bytes.
+pub fn max_chars(value: &str, max: usize) -> bool {
+    value.chars().count() <= max
+}
+
+/// Fills the `{max}` of a message.
+pub fn with_max(message: &str, max: usize) -> String {
+    message.replace("{max}", &max.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_errors() {
+        let mut errors = FieldErrors::default();
+        errors.check("title", present(" "), "required");
+        errors.check("title", max_chars(" ", 0), "too long");
+        errors.check("body", max_chars("本文", 2), "too long");
+        assert!(!errors.is_empty());
+        assert_eq!(errors.get("title"), Some("required"));
+        assert_eq!(errors.get("body"), None);
+        assert_eq!(with_max("at most {max}", 10), "at most 10");
+    }
+}
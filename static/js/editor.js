@@ -0,0 +1,115 @@
+// Renders the Markdown of the article editor into the preview pane as it is typed,
+// and saves its contents automatically while it is open.
+(function () {
+    'use strict';
+
+    var body = document.getElementById('body');
+    var preview = document.getElementById('preview');
+    if (!body || !preview) {
+        return;
+    }
+
+    var DELAY = 300;
+    var timer = null;
+    var sent = body.value;
+    // Responses may arrive out of order; only the latest request is shown.
+    var sequence = 0;
+
+    function render() {
+        timer = null;
+        if (body.value === sent) {
+            return;
+        }
+        sent = body.value;
+        var current = ++sequence;
+        var request = new XMLHttpRequest();
+        request.open('POST', preview.getAttribute('data-url'));
+        request.setRequestHeader('Content-Type', 'application/x-www-form-urlencoded');
+        request.onload = function () {
+            if (current !== sequence) {
+                return;
+            }
+            // A stale preview is kept, marked, e.g. when the login has expired.
+            if (request.status === 200) {
+                preview.innerHTML = request.responseText;
+                preview.classList.remove('failed');
+            } else {
+                preview.classList.add('failed');
+            }
+        };
+        request.onerror = function () {
+            if (current === sequence) {
+                preview.classList.add('failed');
+            }
+        };
+        request.send('body=' + encodeURIComponent(sent));
+    }
+
+    body.addEventListener('input', function () {
+        if (timer !== null) {
+            clearTimeout(timer);
+        }
+        timer = setTimeout(render, DELAY);
+    });
+
+    var form = document.getElementById('article');
+    var status = document.getElementById('autosave-status');
+    var AUTOSAVE_INTERVAL = 10000;
+    var autosaveTimer = null;
+    var autosaveRequest = null;
+    var fields = ['title', 'body', 'summary'];
+
+    function encode() {
+        return fields.map(function (name) {
+            return name + '=' + encodeURIComponent(form.elements[name].value);
+        }).join('&');
+    }
+
+    var autosaved = encode();
+
+    function autosave() {
+        autosaveTimer = null;
+        var data = encode();
+        if (data === autosaved) {
+            return;
+        }
+        var request = new XMLHttpRequest();
+        autosaveRequest = request;
+        request.open('PUT', form.getAttribute('data-autosave'));
+        request.setRequestHeader('Content-Type', 'application/x-www-form-urlencoded');
+        request.onload = function () {
+            autosaveRequest = null;
+            if (request.status === 200) {
+                autosaved = data;
+                status.textContent = request.responseText;
+            } else {
+                status.textContent = status.getAttribute('data-failed');
+            }
+        };
+        request.onerror = function () {
+            autosaveRequest = null;
+            status.textContent = status.getAttribute('data-failed');
+        };
+        request.send(data);
+    }
+
+    // Saves at most once in an interval, starting with the first change after a
+    // save.
+    form.addEventListener('input', function () {
+        if (autosaveTimer === null) {
+            autosaveTimer = setTimeout(autosave, AUTOSAVE_INTERVAL);
+        }
+    });
+    // Saving the article discards the autosave, which a save still on its way
+    // would bring back.
+    form.addEventListener('submit', function () {
+        if (autosaveTimer !== null) {
+            clearTimeout(autosaveTimer);
+            autosaveTimer = null;
+        }
+        if (autosaveRequest !== null) {
+            autosaveRequest.abort();
+            autosaveRequest = null;
+        }
+    });
+}());